@@ -2,6 +2,8 @@ use std::convert;
 use std::fmt;
 use std::str;
 
+use super::Error;
+
 #[derive(Copy, Clone, Debug)]
 pub struct Color {
     pub r: u8,
@@ -12,33 +14,32 @@ pub struct Color {
 impl Color {
     pub fn unpack(i: i32) -> Self {
         Color {
-            r: ((i & (255 << 8 * 2)) >> 8 * 2) as u8,
-            g: ((i & (255 << 8 * 1)) >> 8 * 1) as u8,
-            b: ((i & (255 << 8 * 0)) >> 8 * 0) as u8
+            r: ((i >> 16) & 255) as u8,
+            g: ((i >> 8) & 255) as u8,
+            b: (i & 255) as u8
         }
     }
     pub fn pack(&self) -> i32 {
-        0 |
-            ((self.r as i32) << 8 * 2) |
-            ((self.g as i32) << 8 * 1) |
-            ((self.b as i32) << 8 * 0)
+        ((self.r as i32) << 16) |
+            ((self.g as i32) << 8) |
+            (self.b as i32)
     }
 }
 
 impl str::FromStr for Color {
-    type Err = &'static str;
+    type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut hex = s.to_string();
         let is = if hex.len() != 8 {
             false
         } else {
             let pre: String = hex.drain(..2).collect();
-            pre == "0x".to_string()
+            pre == "0x"
         };
         if is {
-            i32::from_str_radix(&hex, 16).map_err(|_| "not a hexadecimal color").map(|i| Color::unpack(i))
+            i32::from_str_radix(&hex, 16).map_err(|_| Error::ParseColor).map(Color::unpack)
         } else {
-            Err("not a hexadecimal color")
+            Err(Error::ParseColor)
         }
     }
 }
@@ -49,9 +50,9 @@ impl convert::From<i32> for Color {
     }
 }
 
-impl convert::Into<i32> for Color {
-    fn into(self) -> i32 {
-        self.pack()
+impl convert::From<Color> for i32 {
+    fn from(c: Color) -> i32 {
+        c.pack()
     }
 }
 
@@ -0,0 +1,61 @@
+use x11::xlib;
+
+use super::Display;
+use super::Error;
+
+/// Shapes of the standard cursor font
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Shape {
+    X = 0,
+    Arrow = 2,
+    BottomLeftCorner = 12,
+    BottomRightCorner = 14,
+    Crosshair = 34,
+    Fleur = 52,
+    Hand2 = 60,
+    LeftPtr = 68,
+    Sizing = 120,
+    TopLeftCorner = 134,
+    TopRightCorner = 136,
+    Watch = 150,
+    XTerm = 152
+}
+
+/// A pointer cursor
+///
+/// The cursor is freed when the `Cursor` is dropped. The server keeps its
+/// own reference while the cursor is in use, e.g. by a grab.
+pub struct Cursor<'d> {
+    id: u64,
+    d: &'d Display
+}
+
+impl<'d> Cursor<'d> {
+    /// Creates a cursor from the standard cursor font
+    ///
+    /// Returns an error if the server reported an error for the call to
+    /// `XCreateFontCursor()`.
+    pub fn new(d: &'d Display, shape: Shape) -> Result<Self, Error> {
+        let start = d.next_request();
+        // SAFETY: display is valid
+        let id = unsafe { xlib::XCreateFontCursor(d.xlib_display(), shape as u32) };
+        // NOTE: only free the cursor on drop if the server created it
+        d.check(start)?;
+        Ok(Cursor { id, d })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl<'d> Drop for Cursor<'d> {
+    /// Frees the cursor when the `Cursor` is dropped
+    fn drop(&mut self) {
+        // SAFETY: display is valid, the cursor is not used afterwards
+        unsafe {
+            // NOTE: XFreeCursor() is hardcoded to return 1, so ignore it
+            xlib::XFreeCursor(self.d.xlib_display(), self.id);
+        }
+    }
+}
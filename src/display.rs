@@ -1,19 +1,74 @@
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::BorrowedFd;
+use std::os::unix::io::RawFd;
+use std::sync::Mutex;
 use std::ptr;
 use std::ffi;
+use std::mem;
+use std::slice;
+use std::thread;
+use std::time;
 
 use x11::xlib;
 
+use super::Error;
 use super::Screen;
 use super::Window;
 use super::window;
 use super::shapes;
+use super::keyboard;
+use super::grab;
+use super::cursor::Cursor;
+use super::event::Event;
+use super::event::EventMask;
 
-unsafe extern "C" fn x_noop_error_handler(_: *mut xlib::Display, _: *mut xlib::XErrorEvent) -> i32 {
+/// A protocol error reported by the X server
+struct Trapped {
+    /// Address of the xlib display the error occurred on
+    display: usize,
+    /// Serial number of the failed request
+    serial: u64,
+    error: Error
+}
+
+/// Maximum number of uncollected errors kept per display
+///
+/// Errors of requests that nobody checks are never collected, e.g. in an
+/// event loop in unchecked mode, so the oldest errors are dropped instead.
+const MAX_TRAPPED: usize = 64;
+
+/// Protocol errors reported by the X server that were not collected yet
+static TRAPPED_ERRORS: Mutex<Vec<Trapped>> = Mutex::new(Vec::new());
+
+unsafe extern "C" fn x_trap_error_handler(d: *mut xlib::Display, e: *mut xlib::XErrorEvent) -> i32 {
+    // SAFETY: xlib always passes a valid error event
+    let e = unsafe { &*e };
+    let error = Error::ProtocolError {
+        request: e.request_code,
+        minor: e.minor_code,
+        code: e.error_code,
+        resource: e.resourceid
+    };
+    // NOTE: never panic across the FFI boundary, even if the lock is poisoned
+    let mut trapped = TRAPPED_ERRORS.lock().unwrap_or_else(|e| e.into_inner());
+    let display = d as usize;
+    if trapped.iter().filter(|t| t.display == display).count() >= MAX_TRAPPED {
+        if let Some(i) = trapped.iter().position(|t| t.display == display) {
+            trapped.remove(i);
+        }
+    }
+    trapped.push(Trapped { display, serial: e.serial, error });
     0
 }
 
 pub struct Display {
-    d: ptr::NonNull<xlib::Display>
+    d: ptr::NonNull<xlib::Display>,
+    checked: Cell<bool>,
+    hotkeys: RefCell<Vec<keyboard::Hotkey>>,
+    buttons: RefCell<Vec<grab::ButtonGrab>>
 }
 
 impl Display {
@@ -22,18 +77,18 @@ impl Display {
     /// SAFETY:
     /// - the created Display takes ownership of d
     unsafe fn new_unchecked(d: ptr::NonNull<xlib::Display>) -> Self {
-        Display { d }
+        Display { d, checked: Cell::new(false), hotkeys: RefCell::new(vec![]), buttons: RefCell::new(vec![]) }
     }
 
-    fn open_direct(dispname: Option<&ffi::CStr>) -> Result<Self, &'static str> {
+    fn open_direct(dispname: Option<&ffi::CStr>) -> Result<Self, Error> {
         let dispname_ptr = dispname
             .map(ffi::CStr::as_ptr)
             .unwrap_or(ptr::null());
 
         // SAFETY: the passed display name is always a valid ptr or null
         let raw_d = unsafe {
-            // NOTE: register noop error handler to avoid crashes
-            xlib::XSetErrorHandler(Some(x_noop_error_handler));
+            // NOTE: register trapping error handler to avoid crashes
+            xlib::XSetErrorHandler(Some(x_trap_error_handler));
             xlib::XOpenDisplay(dispname_ptr)
         };
 
@@ -41,7 +96,7 @@ impl Display {
             // SAFETY: ownership of d is passed into Display
             Ok(unsafe { Display::new_unchecked(d) })
         } else {
-            Err("XOpenDisplay() failed")
+            Err(Error::OpenDisplay)
         }
     }
 
@@ -49,30 +104,122 @@ impl Display {
     ///
     /// Opens the display `dispname`.
     ///
-    /// Returns an error message if either `dispname` is not a valid
-    /// `std::ffi::CString` or the call to `XOpenDisplay()` returned a NULL
-    /// pointer.
-    pub fn open_named(dispname: &str) -> Result<Self, &'static str> {
-        let cs = ffi::CString::new(dispname)
-            .map_err(|_| "CString::new() failed")?;
-        return Self::open_direct(Some(&cs));
+    /// Returns `Error::CStringNul` if `dispname` is not a valid
+    /// `std::ffi::CString` or `Error::OpenDisplay` if the call to
+    /// `XOpenDisplay()` returned a NULL pointer.
+    pub fn open_named(dispname: &str) -> Result<Self, Error> {
+        let cs = ffi::CString::new(dispname)?;
+        Self::open_direct(Some(&cs))
     }
 
     /// Opens a connection to the Xorg display server
     ///
     /// Opens the display given in the `$DISPLAY` environment variable.
     ///
-    /// Returns an error message if the call to `XOpenDisplay()` returned a
+    /// Returns `Error::OpenDisplay` if the call to `XOpenDisplay()` returned a
     /// NULL pointer
-    pub fn open() -> Result<Self, &'static str> {
-        return Self::open_direct(None);
+    pub fn open() -> Result<Self, Error> {
+        Self::open_direct(None)
     }
 
     pub(super) fn xlib_display(&self) -> *mut xlib::Display {
-            self.d.as_ptr()
+        self.d.as_ptr()
+    }
+
+    /// Returns the serial number the next request will get
+    ///
+    /// Record this before making requests, and pass it to `check()`,
+    /// `sync_since()` or `take_error()` to only collect the errors those
+    /// requests caused.
+    pub(super) fn next_request(&self) -> u64 {
+        // SAFETY: xlib display is valid
+        unsafe { xlib::XNextRequest(self.xlib_display()) }
+    }
+
+    /// Removes the trapped protocol errors of requests since `start`
+    ///
+    /// Errors of earlier requests are kept for a later `sync()`.
+    ///
+    /// Returns the first removed error, if any.
+    pub(super) fn take_error(&self, start: u64) -> Result<(), Error> {
+        let key = self.xlib_display() as usize;
+        let mut trapped = TRAPPED_ERRORS.lock().unwrap_or_else(|e| e.into_inner());
+        let matches = |t: &Trapped| t.display == key && t.serial >= start;
+        let first = trapped.iter()
+            .find(|t| matches(t))
+            .map(|t| t.error.clone());
+        trapped.retain(|t| !matches(t));
+        match first {
+            Some(e) => Err(e),
+            None => Ok(())
+        }
+    }
+
+    /// Waits until all requests have been processed by the server
+    ///
+    /// Calls `XSync()` and reports protocol errors that were trapped since the
+    /// last call to `sync()`, including errors of unchecked requests.
+    ///
+    /// Returns the first trapped `Error::ProtocolError`, if any.
+    pub fn sync(&self) -> Result<(), Error> {
+        self.sync_since(0)
+    }
+
+    /// Waits until all requests have been processed by the server
+    ///
+    /// Like `sync()`, but only reports errors of requests since `start`, see
+    /// `next_request()`.
+    pub(super) fn sync_since(&self, start: u64) -> Result<(), Error> {
+        // SAFETY: xlib display is valid
+        unsafe {
+            // NOTE: XSync() is hardcoded to return 1, so ignore it
+            xlib::XSync(self.xlib_display(), false as i32);
+        }
+        self.take_error(start)
+    }
+
+    /// Sends all buffered requests to the server
+    ///
+    /// Unlike `sync()`, does not wait for the server to process them, e.g.
+    /// to show drawing results without a round trip.
+    pub fn flush(&self) {
+        // SAFETY: xlib display is valid
+        unsafe {
+            // NOTE: XFlush() is hardcoded to return 1, so ignore it
+            xlib::XFlush(self.xlib_display());
+        }
+    }
+
+    /// Checks the requests since `start` if checked mode is enabled
+    ///
+    /// Calls `sync_since()` in checked mode and does nothing otherwise.
+    pub(super) fn check(&self, start: u64) -> Result<(), Error> {
+        if self.checked() {
+            self.sync_since(start)
+        } else {
+            Ok(())
+        }
     }
 
-    pub(super) fn pointer_direct(&self, w: &Window) -> Result<Pointer, &'static str> {
+    /// Enables or disables checked mode
+    ///
+    /// In checked mode, every request made through wlib waits for the server
+    /// to process it and returns the protocol error it caused, if any. This
+    /// costs a round trip per request.
+    ///
+    /// Outside of checked mode, protocol errors are collected and returned by
+    /// the next call to `sync()`. Only the most recent errors are kept, so
+    /// call `sync()` regularly if they matter.
+    pub fn set_checked(&self, checked: bool) {
+        self.checked.set(checked);
+    }
+
+    /// Returns whether checked mode is enabled
+    pub fn checked(&self) -> bool {
+        self.checked.get()
+    }
+
+    pub(super) fn pointer_direct(&self, w: &Window) -> Result<Pointer, Error> {
         let mut root = 0;
         let mut _c = 0;
         let mut pos = shapes::Point::new(0, 0);
@@ -89,7 +236,7 @@ impl Display {
             ) > 0
         };
         if root == 0 /* xlib::None */ {
-            Err("XQueryPointer() failed")
+            Err(Error::CallFailed("XQueryPointer"))
         } else {
             let wpos = if same_screen {
                 Some(wpos)
@@ -97,8 +244,8 @@ impl Display {
                 None
             };
             Ok(Pointer {
-                pos: pos,
-                wpos: wpos
+                pos,
+                wpos
             })
         }
     }
@@ -106,7 +253,7 @@ impl Display {
     /// Pointer coordinates
     ///
     /// Returns an error if the call to `XQueryPointer()` fails.
-    pub fn pointer(&self) -> Result<shapes::Point, &'static str> {
+    pub fn pointer(&self) -> Result<shapes::Point, Error> {
         let scrn = self.screen()?;
         scrn.pointer()
     }
@@ -114,7 +261,7 @@ impl Display {
     /// Moves pointer absolutely
     ///
     /// Returns an error if the call to `XWarpPointer()` fails.
-    pub fn warp_pointer_absolute(&self, p: shapes::Point) -> Result<(), &'static str> {
+    pub fn warp_pointer_absolute(&self, p: shapes::Point) -> Result<(), Error> {
         let scrn = self.screen()?;
         scrn.warp_pointer(p)
     }
@@ -122,7 +269,8 @@ impl Display {
     /// Moves pointer absolutely
     ///
     /// Returns an error if the call to `XWarpPointer()` fails.
-    pub fn warp_pointer_relative(&self, p: shapes::Point) -> Result<(), &'static str> {
+    pub fn warp_pointer_relative(&self, p: shapes::Point) -> Result<(), Error> {
+        let start = self.next_request();
         // SAFETY: xlib display is valid
         let ok = unsafe {
             xlib::XWarpPointer(
@@ -133,9 +281,9 @@ impl Display {
             ) > 0
         };
         if ok {
-            Ok(())
+            self.check(start)
         } else {
-            Err("XWarpPointer() failed")
+            Err(Error::CallFailed("XWarpPointer"))
         }
     }
 
@@ -154,9 +302,9 @@ impl Display {
     ///
     /// Gets the screen with the number `screennum` from the display
     ///
-    /// Returns an error message if the call to `XScreenOfDisplay()` returned a
-    /// NULL pointer.
-    pub fn screen_num<'d>(&'d self, screennum: u32) -> Result<Screen<'d>, &'static str> {
+    /// Returns `Error::NoScreen` if the call to `XScreenOfDisplay()` returned
+    /// a NULL pointer.
+    pub fn screen_num<'d>(&'d self, screennum: u32) -> Result<Screen<'d>, Error> {
         // SAFETY: xlib display is valid
         let raw_s = unsafe {
             xlib::XScreenOfDisplay(self.xlib_display(), screennum as i32)
@@ -166,27 +314,34 @@ impl Display {
             // Screen has a lifetime depending on the Display lifetime
             Ok(unsafe { Screen::new_unchecked(self, s) })
         } else {
-            Err("XScreenOfDisplay() failed")
+            Err(Error::NoScreen(screennum))
         }
     }
 
     /// Get the default screen associated with the display
     ///
-    /// Returns an error message if the call to `XScreenOfDisplay()` returned a
+    /// Returns an error if the call to `XScreenOfDisplay()` returned a
     /// NULL pointer.
-    pub fn screen<'d>(&'d self) -> Result<Screen<'d>, &'static str> {
+    pub fn screen<'d>(&'d self) -> Result<Screen<'d>, Error> {
         self.screen_num(self.screen_default())
     }
 
     /// Gets the window with the specified window id
     ///
     /// Returns an error if the window does not exist.
-    pub fn window<'d>(&'d self, id: window::ID) -> Result<Window<'d>, &'static str> {
-        Window::new(&self, id)
+    pub fn window<'d>(&'d self, id: window::ID) -> Result<Window<'d>, Error> {
+        Window::new(self, id)
     }
 
     /// Gets the currently focused window
-    pub fn focus<'d>(&'d self) -> Result<Option<Window<'d>>, &'static str> {
+    ///
+    /// The window is `None` if no window or the root window under the
+    /// pointer has the focus. Also returns where the focus reverts to when
+    /// the focused window becomes unviewable.
+    ///
+    /// Returns an error if the call to `XGetInputFocus()` failed or the
+    /// focused window gives an error at the `XGetWindowAttributes()` call.
+    pub fn focus<'d>(&'d self) -> Result<Focus<'d>, Error> {
         let mut id = 0;
         let mut revert = 0;
         // SAFETY: xlib display is valid
@@ -196,25 +351,242 @@ impl Display {
         if ok {
             const NONE: u64 = 0; /* xlib::None, which is commented out for no reason */
             const POINTER_ROOT: u64 = xlib::PointerRoot as u64;
-            match id {
-                NONE  => Ok(None),
-                POINTER_ROOT => Ok(None),
-                i => Window::new(&self, i.into()).map(|w| Some(w))
+            let window = match id {
+                NONE  => None,
+                POINTER_ROOT => None,
+                i => Some(Window::new(self, i.into())?)
+            };
+            Ok(Focus { window, revert_to: RevertTo::from_result(revert) })
+        } else {
+            Err(Error::CallFailed("XGetInputFocus"))
+        }
+    }
+
+    /// Becomes the window manager of a screen
+    ///
+    /// Selects `SubstructureRedirectMask` and `SubstructureNotifyMask` on the
+    /// root window, so that `MapRequest`, `ConfigureRequest` and
+    /// `CirculateRequest` events are sent to this client instead of being
+    /// carried out by the server.
+    ///
+    /// Returns the root window, `Error::WmRunning` if another window manager
+    /// is already running, or an error if selecting the events failed.
+    pub fn become_wm<'d>(&'d self, scrn: &Screen<'d>) -> Result<Window<'d>, Error> {
+        let mut root = scrn.root()?;
+        let mask = root.event_mask() | EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY;
+        let start = self.next_request();
+        root.select_input(mask)
+            .and_then(|_| self.sync_since(start))
+            .map_err(|e| match e {
+                Error::ProtocolError { code, .. } if code == xlib::BadAccess => Error::WmRunning,
+                e => e
+            })?;
+        Ok(root)
+    }
+
+    /// Grabs the pointer for `window`
+    ///
+    /// Pointer events selected by `event_mask` are reported to this client
+    /// relative to `window` until the returned guard is dropped, e.g. while
+    /// interactively moving or resizing a window. The pointer is kept inside
+    /// `confine_to` and shows `cursor`, if given. With `grab::Mode::Sync`,
+    /// pointer events are frozen until `allow_events()` is called.
+    ///
+    /// Returns `Error::Grab` if the server refused the grab, or an error if
+    /// the server reported one.
+    pub fn grab_pointer<'d>(&'d self, window: &Window, event_mask: EventMask, mode: grab::Mode, confine_to: Option<&Window>, cursor: Option<&Cursor>) -> Result<grab::PointerGrab<'d>, Error> {
+        let start = self.next_request();
+        // SAFETY: xlib display is valid
+        let status = unsafe {
+            xlib::XGrabPointer(
+                self.xlib_display(), window.id().into(), false as i32, event_mask.bits() as u32,
+                mode as i32, xlib::GrabModeAsync,
+                confine_to.map_or(0, |w| w.id().into()), cursor.map_or(0, Cursor::id),
+                xlib::CurrentTime
+            )
+        };
+        match grab::Status::from_result(status) {
+            Some(status) => Err(Error::Grab(status)),
+            None => {
+                let grab = grab::PointerGrab::new(self);
+                self.check(start)?;
+                Ok(grab)
             }
+        }
+    }
+
+    /// Releases a pointer grab
+    ///
+    /// Usually called by dropping the guard returned by `grab_pointer()`, but
+    /// also releases grabs activated by `Window::grab_button()`.
+    pub fn ungrab_pointer(&self) {
+        // SAFETY: xlib display is valid
+        unsafe {
+            // NOTE: XUngrabPointer() is hardcoded to return 1, so ignore it
+            xlib::XUngrabPointer(self.xlib_display(), xlib::CurrentTime);
+        }
+    }
+
+    /// Releases events frozen by a synchronous grab
+    ///
+    /// Returns an error if the server reported one.
+    pub fn allow_events(&self, mode: grab::AllowEvents) -> Result<(), Error> {
+        let start = self.next_request();
+        // SAFETY: xlib display is valid
+        unsafe {
+            // NOTE: XAllowEvents() is hardcoded to return 1, so ignore it
+            xlib::XAllowEvents(self.xlib_display(), mode as i32, xlib::CurrentTime);
+        }
+        self.check(start)
+    }
+
+    /// Grabs the keyboard for `window`
+    ///
+    /// All key events are reported to this client until the returned guard
+    /// is dropped, e.g. for launchers and menus. If another client holds a
+    /// grab, retries until `timeout` has passed, since such grabs are often
+    /// released shortly after, e.g. by the hotkey daemon that started this
+    /// client. Waits 10ms before the first retry, doubling up to 160ms.
+    ///
+    /// Returns `Error::Grab` if the server still refused the grab after
+    /// `timeout`, or an error if the server reported one.
+    pub fn grab_keyboard<'d>(&'d self, window: &Window, timeout: time::Duration) -> Result<grab::KeyboardGrab<'d>, Error> {
+        // NOTE: back off between attempts instead of flooding the server
+        const MAX_DELAY: time::Duration = time::Duration::from_millis(160);
+        let begin = time::Instant::now();
+        let mut delay = time::Duration::from_millis(10);
+        loop {
+            let start = self.next_request();
+            // SAFETY: xlib display is valid
+            let status = unsafe {
+                xlib::XGrabKeyboard(
+                    self.xlib_display(), window.id().into(), true as i32,
+                    xlib::GrabModeAsync, xlib::GrabModeAsync, xlib::CurrentTime
+                )
+            };
+            match grab::Status::from_result(status) {
+                None => {
+                    let grab = grab::KeyboardGrab::new(self);
+                    self.check(start)?;
+                    return Ok(grab);
+                },
+                Some(grab::Status::AlreadyGrabbed) | Some(grab::Status::Frozen) if begin.elapsed() < timeout => {
+                    // NOTE: make the last attempt right at the timeout
+                    thread::sleep(delay.min(timeout.saturating_sub(begin.elapsed())));
+                    delay = (delay * 2).min(MAX_DELAY);
+                },
+                Some(status) => return Err(Error::Grab(status))
+            }
+        }
+    }
+
+    /// Releases a keyboard grab
+    ///
+    /// Usually called by dropping the guard returned by `grab_keyboard()`.
+    pub fn ungrab_keyboard(&self) {
+        // SAFETY: xlib display is valid
+        unsafe {
+            // NOTE: XUngrabKeyboard() is hardcoded to return 1, so ignore it
+            xlib::XUngrabKeyboard(self.xlib_display(), xlib::CurrentTime);
+        }
+    }
+
+    /// Returns the number of events that have not been read yet
+    ///
+    /// Flushes the output buffer and reads pending events from the
+    /// connection, but does not block.
+    pub fn pending(&self) -> u32 {
+        // SAFETY: xlib display is valid
+        unsafe { xlib::XPending(self.xlib_display()) as u32 }
+    }
+
+    /// Waits for the next event
+    ///
+    /// Flushes the output buffer and blocks until an event is received. Key
+    /// events caused by a grab from `Window::grab_key()` carry the grabbed
+    /// combination.
+    pub fn next_event(&self) -> Event {
+        // SAFETY: zeroed XEvent is valid
+        let mut e: xlib::XEvent = unsafe { mem::zeroed() };
+        // SAFETY: xlib display is valid
+        unsafe {
+            // NOTE: XNextEvent() is hardcoded to return 0, so ignore it
+            xlib::XNextEvent(self.xlib_display(), &mut e);
+        }
+        let mut ev = Event::from_xevent(&e);
+        if let Event::KeyPress(ref mut k) | Event::KeyRelease(ref mut k) = ev {
+            k.combo = self.hotkeys.borrow().iter()
+                .find(|h| h.matches(k.window, k.keycode, k.state))
+                .map(|h| h.combo);
+        }
+        ev
+    }
+
+    /// Remembers a key grab, so that its events can be tagged with the combo
+    ///
+    /// Replaces an earlier grab of the same combo on the same window.
+    pub(super) fn add_hotkey(&self, hotkey: keyboard::Hotkey) {
+        let mut hotkeys = self.hotkeys.borrow_mut();
+        match hotkeys.iter_mut().find(|h| h.window == hotkey.window && h.combo == hotkey.combo) {
+            Some(h) => *h = hotkey,
+            None => hotkeys.push(hotkey)
+        }
+    }
+
+    /// Returns the key grab of `combo` on `window`, if there is one
+    pub(super) fn hotkey(&self, window: window::ID, combo: keyboard::KeyCombo) -> Option<keyboard::Hotkey> {
+        self.hotkeys.borrow().iter()
+            .find(|h| h.window == window && h.combo == combo)
+            .cloned()
+    }
+
+    /// Forgets the key grabs of `combo` on `window`
+    pub(super) fn remove_hotkey(&self, window: window::ID, combo: keyboard::KeyCombo) {
+        self.hotkeys.borrow_mut().retain(|h| h.window != window || h.combo != combo);
+    }
+
+    /// Remembers a button grab, replacing an earlier grab of the same button
+    /// and modifiers on the same window
+    pub(super) fn add_button_grab(&self, grab: grab::ButtonGrab) {
+        let mut buttons = self.buttons.borrow_mut();
+        match buttons.iter_mut().find(|b| b.window == grab.window && b.button == grab.button && b.modifiers == grab.modifiers) {
+            Some(b) => *b = grab,
+            None => buttons.push(grab)
+        }
+    }
+
+    /// Returns the grab of `button` with `modifiers` on `window`, if there is
+    /// one
+    pub(super) fn button_grab(&self, window: window::ID, button: u32, modifiers: keyboard::Modifiers) -> Option<grab::ButtonGrab> {
+        self.buttons.borrow().iter()
+            .find(|b| b.window == window && b.button == button && b.modifiers == modifiers)
+            .copied()
+    }
+
+    /// Forgets the grab of `button` with `modifiers` on `window`
+    pub(super) fn remove_button_grab(&self, window: window::ID, button: u32, modifiers: keyboard::Modifiers) {
+        self.buttons.borrow_mut().retain(|b| b.window != window || b.button != button || b.modifiers != modifiers);
+    }
+
+    /// Returns the next event if one is available
+    ///
+    /// Does not block, returns `None` if `pending()` is zero.
+    pub fn poll_event(&self) -> Option<Event> {
+        if self.pending() > 0 {
+            Some(self.next_event())
         } else {
-            Err("XGetInputFocus() failed")
+            None
         }
     }
 
-    pub fn atom(&self, name: &str) -> Result<Atom, &'static str> {
-        let cs = ffi::CString::new(name)
-            .map_err(|_| "CString::new() failed")?;
+    pub fn atom(&self, name: &str) -> Result<Atom, Error> {
+        let cs = ffi::CString::new(name)?;
         // SAFETY: xlib display and passed string are valid
         let atom = unsafe {
             xlib::XInternAtom(self.xlib_display(), cs.as_ptr(), false as i32)
         };
         if atom == 0 /* xlib::None */ {
-            Err("XInternAtom() failed")
+            Err(Error::CallFailed("XInternAtom"))
         } else {
             Ok(Atom {
                 id: atom,
@@ -222,6 +594,135 @@ impl Display {
             })
         }
     }
+
+    /// Looks up the name of the atom with the given id
+    ///
+    /// Returns an error if the call to `XGetAtomName()` fails, e.g. if no
+    /// atom with that id exists.
+    pub fn atom_from_id(&self, id: u64) -> Result<Atom, Error> {
+        let start = self.next_request();
+        // SAFETY: xlib display is valid
+        let raw_name = unsafe {
+            xlib::XGetAtomName(self.xlib_display(), id)
+        };
+        if raw_name.is_null() {
+            // NOTE: report the trapped error instead, if there is one
+            self.take_error(start)?;
+            Err(Error::CallFailed("XGetAtomName"))
+        } else {
+            // SAFETY: the returned name is a valid C string, which is freed
+            // after copying it
+            let name = unsafe {
+                let name = ffi::CStr::from_ptr(raw_name).to_string_lossy().into_owned();
+                xlib::XFree(raw_name as *mut _);
+                name
+            };
+            Ok(Atom { id, name })
+        }
+    }
+
+    /// Returns the keycode of a key producing `keysym`
+    ///
+    /// Returns `Error::NoKeycode` if no key produces the keysym.
+    pub fn keycode(&self, keysym: keyboard::Keysym) -> Result<u32, Error> {
+        // SAFETY: xlib display is valid
+        let keycode = unsafe { xlib::XKeysymToKeycode(self.xlib_display(), keysym.0) };
+        if keycode == 0 {
+            Err(Error::NoKeycode(keysym))
+        } else {
+            Ok(keycode as u32)
+        }
+    }
+
+    /// Returns the keysyms produced by a key, without `NoSymbol` entries
+    ///
+    /// Returns `Error::KeycodeRange` if the server does not use the keycode,
+    /// or an error if the call to `XGetKeyboardMapping()` failed.
+    pub fn keysyms(&self, keycode: u32) -> Result<Vec<keyboard::Keysym>, Error> {
+        Ok(self.keyboard_mapping(keycode, 1)?
+            .swap_remove(0)
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// Returns the keysym at `index` of a key, replacing the deprecated
+    /// `XKeycodeToKeysym()`
+    ///
+    /// Index 0 is the unshifted keysym and index 1 the shifted keysym of the
+    /// first group. Returns `None` if the key has no keysym at `index`.
+    ///
+    /// Returns `Error::KeycodeRange` if the server does not use the keycode,
+    /// or an error if the call to `XGetKeyboardMapping()` failed.
+    pub fn keysym(&self, keycode: u32, index: usize) -> Result<Option<keyboard::Keysym>, Error> {
+        Ok(self.keyboard_mapping(keycode, 1)?
+            .swap_remove(0)
+            .get(index)
+            .copied()
+            .flatten())
+    }
+
+    /// Returns the range of keycodes used by the server
+    pub(super) fn keycode_range(&self) -> (u32, u32) {
+        let (mut min, mut max) = (0, 0);
+        // SAFETY: xlib display is valid
+        unsafe {
+            // NOTE: XDisplayKeycodes() is hardcoded to return 1, so ignore it
+            xlib::XDisplayKeycodes(self.xlib_display(), &mut min, &mut max);
+        }
+        (min as u32, max as u32)
+    }
+
+    /// Returns all keysym entries of `count` keys starting at `first`, one
+    /// row per key, with `NoSymbol` as `None`
+    ///
+    /// Returns `Error::KeycodeRange` if any of the keys is outside
+    /// `keycode_range()`.
+    pub(super) fn keyboard_mapping(&self, first: u32, count: u32) -> Result<Vec<Vec<Option<keyboard::Keysym>>>, Error> {
+        let (min, max) = self.keycode_range();
+        if first < min || first > max {
+            return Err(Error::KeycodeRange(first));
+        }
+        if count == 0 || count - 1 > max - first {
+            return Err(Error::KeycodeRange(first.saturating_add(count)));
+        }
+        let mut n = 0;
+        let start = self.next_request();
+        // SAFETY: xlib display is valid
+        let syms = unsafe {
+            xlib::XGetKeyboardMapping(self.xlib_display(), first as u8, count as i32, &mut n)
+        };
+        if syms.is_null() {
+            // NOTE: report the trapped error instead, if there is one
+            self.take_error(start)?;
+            return Err(Error::CallFailed("XGetKeyboardMapping"));
+        }
+        let n = n.max(1) as usize;
+        // SAFETY: syms has n entries for each of the requested keycodes and
+        // is freed after copying them
+        let rows = unsafe {
+            let v = slice::from_raw_parts(syms, n * count as usize)
+                .chunks(n)
+                .map(|row| row.iter()
+                    .map(|&k| Some(keyboard::Keysym(k)).filter(|k| k.0 != 0 /* xlib::NoSymbol */))
+                    .collect())
+                .collect();
+            xlib::XFree(syms as *mut _);
+            v
+        };
+        Ok(rows)
+    }
+
+    /// Reads which keys set which modifier
+    ///
+    /// Use this to find out which of `Mod1` to `Mod5` is NumLock, Alt or
+    /// Super on this server.
+    ///
+    /// Returns an error if the call to `XGetModifierMapping()` or
+    /// `XGetKeyboardMapping()` failed.
+    pub fn modifier_map(&self) -> Result<keyboard::ModifierMap, Error> {
+        keyboard::ModifierMap::read(self)
+    }
 }
 
 impl Drop for Display {
@@ -232,16 +733,69 @@ impl Drop for Display {
             // NOTE: XCloseDisplay() is hardcoded to return 0, so ignore it
             xlib::XCloseDisplay(self.xlib_display());
         }
+        // NOTE: drop errors that were never reported, a new display may
+        // reuse the address
+        let _ = self.take_error(0);
     }
 }
 
+impl AsRawFd for Display {
+    /// Returns the file descriptor of the connection to the X server
+    ///
+    /// Use `pending()` before waiting for it to become readable, since xlib
+    /// may have queued events already.
+    fn as_raw_fd(&self) -> RawFd {
+        // SAFETY: xlib display is valid
+        unsafe { xlib::XConnectionNumber(self.xlib_display()) }
+    }
+}
+
+impl AsFd for Display {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // SAFETY: the connection stays open for the lifetime of the Display
+        unsafe { BorrowedFd::borrow_raw(self.as_raw_fd()) }
+    }
+}
+
+/// Where the focus reverts to when the focused window becomes unviewable
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RevertTo {
+    None = 0,
+    PointerRoot = 1,
+    Parent = 2
+}
+
+impl RevertTo {
+    fn from_result(revert: i32) -> Self {
+        match revert {
+            xlib::RevertToPointerRoot => RevertTo::PointerRoot,
+            xlib::RevertToParent => RevertTo::Parent,
+            _ => RevertTo::None
+        }
+    }
+}
+
+/// The keyboard focus, as returned by `Display::focus()`
+pub struct Focus<'d> {
+    /// The focused window, if any
+    pub window: Option<Window<'d>>,
+    pub revert_to: RevertTo
+}
+
 pub(super) struct Pointer {
     pub(super) pos: shapes::Point,
     pub(super) wpos: Option<shapes::Point>
 }
 
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Atom {
     id: u64,
     pub name: String
 }
+
+impl Atom {
+    /// Returns the id of the atom, as found in events
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
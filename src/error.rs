@@ -0,0 +1,118 @@
+use std::error;
+use std::ffi;
+use std::fmt;
+
+use super::window;
+use super::shapes;
+use super::keyboard;
+use super::grab;
+
+/// Errors returned by wlib
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The call to `XOpenDisplay()` returned a NULL pointer
+    OpenDisplay,
+    /// A string passed to Xlib contained an interior NUL byte
+    CStringNul,
+    /// The screen with the given number does not exist
+    NoScreen(u32),
+    /// The window with the given id does not exist
+    BadWindow(window::ID),
+    /// The window does not have the named property
+    PropertyMissing(String),
+    /// The named property has an unexpected type or format
+    PropertyType(String),
+    /// The named string property is not encoded correctly
+    PropertyEncoding(String),
+    /// The window does not support the named `WM_PROTOCOLS` protocol
+    ProtocolUnsupported(String),
+    /// Another window manager already selected `SubstructureRedirectMask` on
+    /// the root window
+    WmRunning,
+    /// An Xlib function reported failure through its return value
+    CallFailed(&'static str),
+    /// The X server answered a request with an error
+    ///
+    /// Contains the error code, the major and minor opcode of the failed
+    /// request, and the resource id the request referred to.
+    ProtocolError {
+        request: u8,
+        minor: u8,
+        code: u8,
+        resource: u64
+    },
+    /// The window is not on the same screen as the pointer
+    NotSameScreen,
+    /// InputOnly windows cannot have a border
+    InputOnlyBorder,
+    /// The string is not a hexadecimal color of the form `0xRRGGBB`
+    ParseColor,
+    /// The string is not a hexadecimal number of the form `0x...`
+    ParseID,
+    /// The string is not a keysym name or key combination like
+    /// `Mod4+Shift+Return`
+    ParseKey,
+    /// No key of the keyboard produces the keysym
+    NoKeycode(keyboard::Keysym),
+    /// The keycode is outside the range of keycodes used by the server
+    KeycodeRange(u32),
+    /// The server refused a pointer or keyboard grab
+    Grab(grab::Status),
+    /// The point does not fit into the 16-bit coordinates of the protocol
+    PointRange(shapes::Point),
+    /// The size does not fit into the 16-bit sizes of the protocol
+    SizeRange(shapes::Rectangle)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::OpenDisplay => write!(f, "XOpenDisplay() failed"),
+            Error::CStringNul => write!(f, "string contains a NUL byte"),
+            Error::NoScreen(n) => write!(f, "screen {} does not exist", n),
+            Error::BadWindow(id) => write!(f, "window {} does not exist", id),
+            Error::PropertyMissing(name) => write!(f, "property {} is not set", name),
+            Error::PropertyType(name) => write!(f, "property {} has an unexpected type", name),
+            Error::PropertyEncoding(name) => write!(f, "property {} is not encoded correctly", name),
+            Error::ProtocolUnsupported(name) => write!(f, "window does not support {}", name),
+            Error::WmRunning => write!(f, "another window manager is already running"),
+            Error::CallFailed(func) => write!(f, "{}() failed", func),
+            Error::ProtocolError { request, minor, code, resource } => write!(
+                f, "X protocol error {} in request {}.{} on resource {:#x}",
+                protocol_error_name(*code), request, minor, resource
+            ),
+            Error::NotSameScreen => write!(f, "window not on same screen as pointer"),
+            Error::InputOnlyBorder => write!(f, "InputOnly window with nonzero border width"),
+            Error::ParseColor => write!(f, "not a hexadecimal color"),
+            Error::ParseID => write!(f, "not a hexadecimal number"),
+            Error::ParseKey => write!(f, "not a key or key combination"),
+            Error::NoKeycode(keysym) => write!(f, "no key produces keysym {}", keysym),
+            Error::KeycodeRange(keycode) => write!(f, "keycode {} is out of range", keycode),
+            Error::Grab(status) => write!(f, "grab failed: {}", status),
+            Error::PointRange(p) => write!(f, "point ({}, {}) is out of range", p.x, p.y),
+            Error::SizeRange(r) => write!(f, "size {}x{} is out of range", r.w, r.h)
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// Returns the name of a core protocol error code
+fn protocol_error_name(code: u8) -> String {
+    const NAMES: [&str; 17] = [
+        "BadRequest", "BadValue", "BadWindow", "BadPixmap", "BadAtom",
+        "BadCursor", "BadFont", "BadMatch", "BadDrawable", "BadAccess",
+        "BadAlloc", "BadColor", "BadGC", "BadIDChoice", "BadName",
+        "BadLength", "BadImplementation"
+    ];
+    match code {
+        1..=17 => NAMES[code as usize - 1].to_string(),
+        c => c.to_string()
+    }
+}
+
+impl From<ffi::NulError> for Error {
+    fn from(_: ffi::NulError) -> Self {
+        Error::CStringNul
+    }
+}
@@ -0,0 +1,606 @@
+use bitflags::bitflags;
+use x11::xlib;
+
+use super::Display;
+use super::Error;
+use super::window;
+use super::shapes;
+use super::keyboard;
+
+/// Converts a window id from an event, mapping `None` to `Option::None`
+fn optional_id(w: xlib::Window) -> Option<window::ID> {
+    if w == 0 /* xlib::None */ {
+        None
+    } else {
+        Some(w.into())
+    }
+}
+
+bitflags! {
+    /// Selects the events reported for a window
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+    pub struct EventMask: i64 {
+        const KEY_PRESS = xlib::KeyPressMask;
+        const KEY_RELEASE = xlib::KeyReleaseMask;
+        const BUTTON_PRESS = xlib::ButtonPressMask;
+        const BUTTON_RELEASE = xlib::ButtonReleaseMask;
+        const ENTER_WINDOW = xlib::EnterWindowMask;
+        const LEAVE_WINDOW = xlib::LeaveWindowMask;
+        const POINTER_MOTION = xlib::PointerMotionMask;
+        const POINTER_MOTION_HINT = xlib::PointerMotionHintMask;
+        const BUTTON1_MOTION = xlib::Button1MotionMask;
+        const BUTTON2_MOTION = xlib::Button2MotionMask;
+        const BUTTON3_MOTION = xlib::Button3MotionMask;
+        const BUTTON4_MOTION = xlib::Button4MotionMask;
+        const BUTTON5_MOTION = xlib::Button5MotionMask;
+        const BUTTON_MOTION = xlib::ButtonMotionMask;
+        const KEYMAP_STATE = xlib::KeymapStateMask;
+        const EXPOSURE = xlib::ExposureMask;
+        const VISIBILITY_CHANGE = xlib::VisibilityChangeMask;
+        const STRUCTURE_NOTIFY = xlib::StructureNotifyMask;
+        const RESIZE_REDIRECT = xlib::ResizeRedirectMask;
+        const SUBSTRUCTURE_NOTIFY = xlib::SubstructureNotifyMask;
+        const SUBSTRUCTURE_REDIRECT = xlib::SubstructureRedirectMask;
+        const FOCUS_CHANGE = xlib::FocusChangeMask;
+        const PROPERTY_CHANGE = xlib::PropertyChangeMask;
+        const COLORMAP_CHANGE = xlib::ColormapChangeMask;
+        const OWNER_GRAB_BUTTON = xlib::OwnerGrabButtonMask;
+    }
+}
+
+/// An event received from the X server
+#[derive(Clone, Debug)]
+pub enum Event {
+    KeyPress(KeyEvent),
+    KeyRelease(KeyEvent),
+    ButtonPress(ButtonEvent),
+    ButtonRelease(ButtonEvent),
+    MotionNotify(MotionEvent),
+    EnterNotify(CrossingEvent),
+    LeaveNotify(CrossingEvent),
+    FocusIn(FocusEvent),
+    FocusOut(FocusEvent),
+    Expose {
+        window: window::ID,
+        area: shapes::PositionedRectangle,
+        /// Number of `Expose` events that follow for this window
+        count: u32
+    },
+    CreateNotify {
+        parent: window::ID,
+        window: window::ID,
+        geometry: shapes::PositionedRectangle,
+        border_width: u32,
+        override_redirect: bool
+    },
+    DestroyNotify {
+        event: window::ID,
+        window: window::ID
+    },
+    UnmapNotify {
+        event: window::ID,
+        window: window::ID,
+        from_configure: bool
+    },
+    MapNotify {
+        event: window::ID,
+        window: window::ID,
+        override_redirect: bool
+    },
+    MapRequest(MapRequestEvent),
+    ReparentNotify {
+        event: window::ID,
+        window: window::ID,
+        parent: window::ID,
+        position: shapes::Point,
+        override_redirect: bool
+    },
+    ConfigureNotify {
+        event: window::ID,
+        window: window::ID,
+        geometry: shapes::PositionedRectangle,
+        border_width: u32,
+        /// The sibling the window is stacked above, if any
+        above: Option<window::ID>,
+        override_redirect: bool
+    },
+    ConfigureRequest(ConfigureRequestEvent),
+    CirculateRequest(CirculateRequestEvent),
+    PropertyNotify {
+        window: window::ID,
+        /// Id of the atom naming the changed property
+        atom: u64,
+        time: xlib::Time,
+        deleted: bool
+    },
+    ClientMessage(ClientMessageEvent),
+    /// Any event type wlib does not decode, with its event type number
+    Other(i32)
+}
+
+impl Event {
+    /// Decodes an xlib event
+    pub(super) fn from_xevent(e: &xlib::XEvent) -> Self {
+        // SAFETY: the union field accessed always matches the event type
+        unsafe {
+            match e.get_type() {
+                xlib::KeyPress => Event::KeyPress(KeyEvent::from(&e.key)),
+                xlib::KeyRelease => Event::KeyRelease(KeyEvent::from(&e.key)),
+                xlib::ButtonPress => Event::ButtonPress(ButtonEvent::from(&e.button)),
+                xlib::ButtonRelease => Event::ButtonRelease(ButtonEvent::from(&e.button)),
+                xlib::MotionNotify => Event::MotionNotify(MotionEvent::from(&e.motion)),
+                xlib::EnterNotify => Event::EnterNotify(CrossingEvent::from(&e.crossing)),
+                xlib::LeaveNotify => Event::LeaveNotify(CrossingEvent::from(&e.crossing)),
+                xlib::FocusIn => Event::FocusIn(FocusEvent::from(&e.focus_change)),
+                xlib::FocusOut => Event::FocusOut(FocusEvent::from(&e.focus_change)),
+                xlib::Expose => {
+                    let ev = &e.expose;
+                    Event::Expose {
+                        window: ev.window.into(),
+                        area: shapes::PositionedRectangle::new(ev.x, ev.y, ev.width as u32, ev.height as u32),
+                        count: ev.count as u32
+                    }
+                },
+                xlib::CreateNotify => {
+                    let ev = &e.create_window;
+                    Event::CreateNotify {
+                        parent: ev.parent.into(),
+                        window: ev.window.into(),
+                        geometry: shapes::PositionedRectangle::new(ev.x, ev.y, ev.width as u32, ev.height as u32),
+                        border_width: ev.border_width as u32,
+                        override_redirect: ev.override_redirect != 0
+                    }
+                },
+                xlib::DestroyNotify => {
+                    let ev = &e.destroy_window;
+                    Event::DestroyNotify {
+                        event: ev.event.into(),
+                        window: ev.window.into()
+                    }
+                },
+                xlib::UnmapNotify => {
+                    let ev = &e.unmap;
+                    Event::UnmapNotify {
+                        event: ev.event.into(),
+                        window: ev.window.into(),
+                        from_configure: ev.from_configure != 0
+                    }
+                },
+                xlib::MapNotify => {
+                    let ev = &e.map;
+                    Event::MapNotify {
+                        event: ev.event.into(),
+                        window: ev.window.into(),
+                        override_redirect: ev.override_redirect != 0
+                    }
+                },
+                xlib::MapRequest => Event::MapRequest(MapRequestEvent::from(&e.map_request)),
+                xlib::ReparentNotify => {
+                    let ev = &e.reparent;
+                    Event::ReparentNotify {
+                        event: ev.event.into(),
+                        window: ev.window.into(),
+                        parent: ev.parent.into(),
+                        position: shapes::Point::new(ev.x, ev.y),
+                        override_redirect: ev.override_redirect != 0
+                    }
+                },
+                xlib::ConfigureNotify => {
+                    let ev = &e.configure;
+                    Event::ConfigureNotify {
+                        event: ev.event.into(),
+                        window: ev.window.into(),
+                        geometry: shapes::PositionedRectangle::new(ev.x, ev.y, ev.width as u32, ev.height as u32),
+                        border_width: ev.border_width as u32,
+                        above: optional_id(ev.above),
+                        override_redirect: ev.override_redirect != 0
+                    }
+                },
+                xlib::ConfigureRequest => Event::ConfigureRequest(ConfigureRequestEvent::from(&e.configure_request)),
+                xlib::CirculateRequest => Event::CirculateRequest(CirculateRequestEvent::from(&e.circulate_request)),
+                xlib::PropertyNotify => {
+                    let ev = &e.property;
+                    Event::PropertyNotify {
+                        window: ev.window.into(),
+                        atom: ev.atom,
+                        time: ev.time,
+                        deleted: ev.state == xlib::PropertyDelete
+                    }
+                },
+                xlib::ClientMessage => Event::ClientMessage(ClientMessageEvent::from(&e.client_message)),
+                t => Event::Other(t)
+            }
+        }
+    }
+}
+
+/// Payload of `KeyPress` and `KeyRelease` events
+#[derive(Clone, Debug)]
+pub struct KeyEvent {
+    pub window: window::ID,
+    pub root: window::ID,
+    pub subwindow: Option<window::ID>,
+    pub time: xlib::Time,
+    /// Pointer coordinates relative to `window`
+    pub pos: shapes::Point,
+    /// Pointer coordinates relative to `root`
+    pub root_pos: shapes::Point,
+    /// Modifier and button mask before the event
+    pub state: u32,
+    pub keycode: u32,
+    pub same_screen: bool,
+    /// The combination grabbed with `Window::grab_key()` that caused the
+    /// event, if any
+    pub combo: Option<keyboard::KeyCombo>
+}
+
+impl KeyEvent {
+    /// Returns the keysym of the key after applying Shift and Lock
+    ///
+    /// Returns `None` if the key produces no keysym.
+    pub fn keysym(&self, d: &Display) -> Option<keyboard::Keysym> {
+        keyboard::lookup(d, self.keycode, self.state).0
+    }
+
+    /// Returns the text typed by the key as UTF-8
+    ///
+    /// Uses `XLookupString()`, so input methods and compose sequences are not
+    /// supported. Returns an empty string for keys that produce no text.
+    pub fn lookup_string(&self, d: &Display) -> String {
+        keyboard::lookup(d, self.keycode, self.state).1
+    }
+}
+
+impl From<&xlib::XKeyEvent> for KeyEvent {
+    fn from(ev: &xlib::XKeyEvent) -> Self {
+        KeyEvent {
+            window: ev.window.into(),
+            root: ev.root.into(),
+            subwindow: optional_id(ev.subwindow),
+            time: ev.time,
+            pos: shapes::Point::new(ev.x, ev.y),
+            root_pos: shapes::Point::new(ev.x_root, ev.y_root),
+            state: ev.state,
+            keycode: ev.keycode,
+            same_screen: ev.same_screen != 0,
+            combo: None
+        }
+    }
+}
+
+/// Payload of `ButtonPress` and `ButtonRelease` events
+#[derive(Clone, Debug)]
+pub struct ButtonEvent {
+    pub window: window::ID,
+    pub root: window::ID,
+    pub subwindow: Option<window::ID>,
+    pub time: xlib::Time,
+    /// Pointer coordinates relative to `window`
+    pub pos: shapes::Point,
+    /// Pointer coordinates relative to `root`
+    pub root_pos: shapes::Point,
+    /// Modifier and button mask before the event
+    pub state: u32,
+    pub button: u32,
+    pub same_screen: bool
+}
+
+impl From<&xlib::XButtonEvent> for ButtonEvent {
+    fn from(ev: &xlib::XButtonEvent) -> Self {
+        ButtonEvent {
+            window: ev.window.into(),
+            root: ev.root.into(),
+            subwindow: optional_id(ev.subwindow),
+            time: ev.time,
+            pos: shapes::Point::new(ev.x, ev.y),
+            root_pos: shapes::Point::new(ev.x_root, ev.y_root),
+            state: ev.state,
+            button: ev.button,
+            same_screen: ev.same_screen != 0
+        }
+    }
+}
+
+/// Payload of `MotionNotify` events
+#[derive(Clone, Debug)]
+pub struct MotionEvent {
+    pub window: window::ID,
+    pub root: window::ID,
+    pub subwindow: Option<window::ID>,
+    pub time: xlib::Time,
+    /// Pointer coordinates relative to `window`
+    pub pos: shapes::Point,
+    /// Pointer coordinates relative to `root`
+    pub root_pos: shapes::Point,
+    /// Modifier and button mask during the motion
+    pub state: u32,
+    pub is_hint: bool,
+    pub same_screen: bool
+}
+
+impl From<&xlib::XMotionEvent> for MotionEvent {
+    fn from(ev: &xlib::XMotionEvent) -> Self {
+        MotionEvent {
+            window: ev.window.into(),
+            root: ev.root.into(),
+            subwindow: optional_id(ev.subwindow),
+            time: ev.time,
+            pos: shapes::Point::new(ev.x, ev.y),
+            root_pos: shapes::Point::new(ev.x_root, ev.y_root),
+            state: ev.state,
+            is_hint: ev.is_hint != 0,
+            same_screen: ev.same_screen != 0
+        }
+    }
+}
+
+/// Payload of `EnterNotify` and `LeaveNotify` events
+#[derive(Clone, Debug)]
+pub struct CrossingEvent {
+    pub window: window::ID,
+    pub root: window::ID,
+    pub subwindow: Option<window::ID>,
+    pub time: xlib::Time,
+    /// Pointer coordinates relative to `window`
+    pub pos: shapes::Point,
+    /// Pointer coordinates relative to `root`
+    pub root_pos: shapes::Point,
+    /// One of `NotifyNormal`, `NotifyGrab` or `NotifyUngrab`
+    pub mode: i32,
+    /// One of the `NotifyAncestor` ... `NotifyNonlinearVirtual` constants
+    pub detail: i32,
+    pub same_screen: bool,
+    /// Whether `window` is or contains the focus window
+    pub focus: bool,
+    /// Modifier and button mask at the time of the event
+    pub state: u32
+}
+
+impl From<&xlib::XCrossingEvent> for CrossingEvent {
+    fn from(ev: &xlib::XCrossingEvent) -> Self {
+        CrossingEvent {
+            window: ev.window.into(),
+            root: ev.root.into(),
+            subwindow: optional_id(ev.subwindow),
+            time: ev.time,
+            pos: shapes::Point::new(ev.x, ev.y),
+            root_pos: shapes::Point::new(ev.x_root, ev.y_root),
+            mode: ev.mode,
+            detail: ev.detail,
+            same_screen: ev.same_screen != 0,
+            focus: ev.focus != 0,
+            state: ev.state
+        }
+    }
+}
+
+/// Payload of `FocusIn` and `FocusOut` events
+#[derive(Clone, Debug)]
+pub struct FocusEvent {
+    pub window: window::ID,
+    /// One of `NotifyNormal`, `NotifyWhileGrabbed`, `NotifyGrab` or
+    /// `NotifyUngrab`
+    pub mode: i32,
+    /// One of the `NotifyAncestor` ... `NotifyDetailNone` constants
+    pub detail: i32
+}
+
+impl From<&xlib::XFocusChangeEvent> for FocusEvent {
+    fn from(ev: &xlib::XFocusChangeEvent) -> Self {
+        FocusEvent {
+            window: ev.window.into(),
+            mode: ev.mode,
+            detail: ev.detail
+        }
+    }
+}
+
+/// Payload of `MapRequest` events
+#[derive(Clone, Debug)]
+pub struct MapRequestEvent {
+    pub parent: window::ID,
+    pub window: window::ID
+}
+
+impl MapRequestEvent {
+    /// Grants the request by mapping the window
+    ///
+    /// To deny the request, ignore it.
+    ///
+    /// Returns an error if the call to `XMapWindow()` failed.
+    pub fn grant(&self, d: &Display) -> Result<(), Error> {
+        let start = d.next_request();
+        // SAFETY: display is valid
+        let ok = unsafe {
+            xlib::XMapWindow(d.xlib_display(), self.window.into()) == 1
+        };
+        if ok {
+            d.check(start)
+        } else {
+            Err(Error::CallFailed("XMapWindow"))
+        }
+    }
+}
+
+impl From<&xlib::XMapRequestEvent> for MapRequestEvent {
+    fn from(ev: &xlib::XMapRequestEvent) -> Self {
+        MapRequestEvent {
+            parent: ev.parent.into(),
+            window: ev.window.into()
+        }
+    }
+}
+
+/// Payload of `ConfigureRequest` events
+///
+/// Only the fields selected by `value_mask` were requested by the client.
+#[derive(Clone, Debug)]
+pub struct ConfigureRequestEvent {
+    pub parent: window::ID,
+    pub window: window::ID,
+    pub geometry: shapes::PositionedRectangle,
+    pub border_width: u32,
+    /// The sibling used for restacking, if any
+    pub above: Option<window::ID>,
+    /// The requested stack mode
+    pub detail: i32,
+    /// Combination of the `CWX` ... `CWStackMode` flags
+    pub value_mask: u64
+}
+
+impl ConfigureRequestEvent {
+    /// Returns the requested changes
+    ///
+    /// Only the values selected by `value_mask` are set.
+    pub fn changes(&self) -> window::Changes {
+        let mut c = window::Changes::new();
+        let m = self.value_mask as u16;
+        let g = self.geometry;
+        if m & xlib::CWX != 0 {
+            c.x(g.p.x);
+        }
+        if m & xlib::CWY != 0 {
+            c.y(g.p.y);
+        }
+        if m & xlib::CWWidth != 0 {
+            c.width(g.r.w);
+        }
+        if m & xlib::CWHeight != 0 {
+            c.height(g.r.h);
+        }
+        if m & xlib::CWBorderWidth != 0 {
+            c.border_width(self.border_width);
+        }
+        if let (true, Some(above)) = (m & xlib::CWSibling != 0, self.above) {
+            c.sibling(above);
+        }
+        if m & xlib::CWStackMode != 0 {
+            let mode = match self.detail {
+                xlib::Below => window::StackMode::Below,
+                xlib::TopIf => window::StackMode::TopIf,
+                xlib::BottomIf => window::StackMode::BottomIf,
+                xlib::Opposite => window::StackMode::Opposite,
+                _ => window::StackMode::Above
+            };
+            c.stack(mode);
+        }
+        c
+    }
+
+    /// Grants the request by applying the requested changes
+    ///
+    /// Returns an error if applying the changes failed.
+    pub fn grant(&self, d: &Display) -> Result<(), Error> {
+        self.changes().apply(d, self.window)
+    }
+
+    /// Applies other changes than requested
+    ///
+    /// Typically used with a modified version of `changes()`. Also sends a
+    /// synthetic `ConfigureNotify` to the client, as the changes may not
+    /// cause a real one.
+    ///
+    /// Returns an error if applying the changes or notifying the client
+    /// failed.
+    pub fn modify(&self, d: &Display, c: &window::Changes) -> Result<(), Error> {
+        c.apply(d, self.window)?;
+        d.window(self.window)?.send_configure_notify()
+    }
+
+    /// Denies the request
+    ///
+    /// Sends a synthetic `ConfigureNotify` with the unchanged geometry to the
+    /// client.
+    ///
+    /// Returns an error if notifying the client failed.
+    pub fn deny(&self, d: &Display) -> Result<(), Error> {
+        d.window(self.window)?.send_configure_notify()
+    }
+}
+
+impl From<&xlib::XConfigureRequestEvent> for ConfigureRequestEvent {
+    fn from(ev: &xlib::XConfigureRequestEvent) -> Self {
+        ConfigureRequestEvent {
+            parent: ev.parent.into(),
+            window: ev.window.into(),
+            geometry: shapes::PositionedRectangle::new(ev.x, ev.y, ev.width as u32, ev.height as u32),
+            border_width: ev.border_width as u32,
+            above: optional_id(ev.above),
+            detail: ev.detail,
+            value_mask: ev.value_mask
+        }
+    }
+}
+
+/// Payload of `CirculateRequest` events
+#[derive(Clone, Debug)]
+pub struct CirculateRequestEvent {
+    pub parent: window::ID,
+    pub window: window::ID,
+    /// Whether the window asks to be raised to the top instead of lowered
+    pub on_top: bool
+}
+
+impl CirculateRequestEvent {
+    /// Grants the request by raising or lowering the window
+    ///
+    /// To deny the request, ignore it.
+    ///
+    /// Returns an error if the call to `XRaiseWindow()` or `XLowerWindow()`
+    /// failed.
+    pub fn grant(&self, d: &Display) -> Result<(), Error> {
+        let start = d.next_request();
+        // SAFETY: display is valid
+        let ok = unsafe {
+            if self.on_top {
+                xlib::XRaiseWindow(d.xlib_display(), self.window.into()) == 1
+            } else {
+                xlib::XLowerWindow(d.xlib_display(), self.window.into()) == 1
+            }
+        };
+        if ok {
+            d.check(start)
+        } else if self.on_top {
+            Err(Error::CallFailed("XRaiseWindow"))
+        } else {
+            Err(Error::CallFailed("XLowerWindow"))
+        }
+    }
+}
+
+impl From<&xlib::XCirculateRequestEvent> for CirculateRequestEvent {
+    fn from(ev: &xlib::XCirculateRequestEvent) -> Self {
+        CirculateRequestEvent {
+            parent: ev.parent.into(),
+            window: ev.window.into(),
+            on_top: ev.place == xlib::PlaceOnTop
+        }
+    }
+}
+
+/// Payload of `ClientMessage` events
+#[derive(Clone, Debug)]
+pub struct ClientMessageEvent {
+    pub window: window::ID,
+    /// Id of the atom describing the message
+    pub message_type: u64,
+    /// Either 8, 16 or 32
+    pub format: i32,
+    /// The message data viewed as 32-bit values
+    pub data: [i64; 5]
+}
+
+impl From<&xlib::XClientMessageEvent> for ClientMessageEvent {
+    fn from(ev: &xlib::XClientMessageEvent) -> Self {
+        let mut data = [0; 5];
+        for (i, d) in data.iter_mut().enumerate() {
+            *d = ev.data.get_long(i);
+        }
+        ClientMessageEvent {
+            window: ev.window.into(),
+            message_type: ev.message_type,
+            format: ev.format,
+            data
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use x11::xlib;
+
+use super::Error;
+use super::Screen;
+use super::Window;
+use super::window;
+use super::shapes;
+use super::property;
+use super::event::EventMask;
+
+/// Source indication sent with requests, as pagers and taskbars do
+const SOURCE_PAGER: i64 = 2;
+
+/// Sends a request about `w` to the window manager
+fn request(w: &Window, name: &str, data: [i64; 5]) -> Result<(), Error> {
+    let root = w.screen().root()?;
+    let message_type = w.screen().display().atom(name)?;
+    let mask = EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY;
+    root.send_client_message_direct(w.id(), mask, &message_type, data)
+}
+
+/// What to do with the states in `set_state()`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StateAction {
+    Remove = 0,
+    Add = 1,
+    Toggle = 2
+}
+
+/// A `_NET_WM_STATE` hint
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum State {
+    Modal,
+    Sticky,
+    MaximizedVert,
+    MaximizedHorz,
+    Shaded,
+    SkipTaskbar,
+    SkipPager,
+    Hidden,
+    Fullscreen,
+    Above,
+    Below,
+    DemandsAttention
+}
+
+impl State {
+    /// Returns the name of the atom for this state
+    pub fn atom_name(&self) -> &'static str {
+        match self {
+            State::Modal => "_NET_WM_STATE_MODAL",
+            State::Sticky => "_NET_WM_STATE_STICKY",
+            State::MaximizedVert => "_NET_WM_STATE_MAXIMIZED_VERT",
+            State::MaximizedHorz => "_NET_WM_STATE_MAXIMIZED_HORZ",
+            State::Shaded => "_NET_WM_STATE_SHADED",
+            State::SkipTaskbar => "_NET_WM_STATE_SKIP_TASKBAR",
+            State::SkipPager => "_NET_WM_STATE_SKIP_PAGER",
+            State::Hidden => "_NET_WM_STATE_HIDDEN",
+            State::Fullscreen => "_NET_WM_STATE_FULLSCREEN",
+            State::Above => "_NET_WM_STATE_ABOVE",
+            State::Below => "_NET_WM_STATE_BELOW",
+            State::DemandsAttention => "_NET_WM_STATE_DEMANDS_ATTENTION"
+        }
+    }
+}
+
+/// Desktop number used by `set_desktop()` to show a window on all desktops
+pub const ALL_DESKTOPS: u32 = 0xFFFFFFFF;
+
+/// Reads a property of the root window of the screen
+fn root_property(scrn: &Screen, name: &str, ty: &str) -> Result<property::Property, Error> {
+    let d = scrn.display();
+    let root = scrn.root()?;
+    root.get_property(&d.atom(name)?, Some(&d.atom(ty)?))
+}
+
+/// Reads a single `CARDINAL` from the root window of the screen
+fn root_cardinal(scrn: &Screen, name: &str) -> Result<u32, Error> {
+    root_property(scrn, name, "CARDINAL")?
+        .to_u32s()?
+        .first()
+        .copied()
+        .ok_or(Error::PropertyType(name.to_string()))
+}
+
+/// Gets the windows with the given ids, skipping windows that do not exist
+fn windows<'d>(scrn: &Screen<'d>, ids: Vec<window::ID>) -> Result<Vec<Window<'d>>, Error> {
+    let d = scrn.display();
+    let mut windows = vec![];
+    for id in ids {
+        match d.window(id) {
+            Ok(w) => windows.push(w),
+            // NOTE: the window may have been destroyed since the list was set
+            Err(Error::BadWindow(_)) => {},
+            Err(e) => return Err(e)
+        }
+    }
+    Ok(windows)
+}
+
+/// Returns the windows managed by the window manager in mapping order
+///
+/// Reads `_NET_CLIENT_LIST`. Windows that were destroyed since the window
+/// manager updated the list are skipped.
+///
+/// Returns an error if the property is not set or reading it failed.
+pub fn client_list<'d>(scrn: &Screen<'d>) -> Result<Vec<Window<'d>>, Error> {
+    let ids = root_property(scrn, "_NET_CLIENT_LIST", "WINDOW")?.to_ids()?;
+    windows(scrn, ids)
+}
+
+/// Returns the windows managed by the window manager in stacking order
+///
+/// Reads `_NET_CLIENT_LIST_STACKING`, from bottom to top. Windows that were
+/// destroyed since the window manager updated the list are skipped.
+///
+/// Returns an error if the property is not set or reading it failed.
+pub fn client_list_stacking<'d>(scrn: &Screen<'d>) -> Result<Vec<Window<'d>>, Error> {
+    let ids = root_property(scrn, "_NET_CLIENT_LIST_STACKING", "WINDOW")?.to_ids()?;
+    windows(scrn, ids)
+}
+
+/// Returns the active window
+///
+/// Reads `_NET_ACTIVE_WINDOW`. Returns `None` if no window is active.
+///
+/// Returns an error if the property is not set or reading it failed.
+pub fn active_window<'d>(scrn: &Screen<'d>) -> Result<Option<Window<'d>>, Error> {
+    let ids = root_property(scrn, "_NET_ACTIVE_WINDOW", "WINDOW")?.to_ids()?;
+    match ids.first() {
+        None | Some(window::ID(0)) => Ok(None),
+        Some(&id) => scrn.display().window(id).map(Some)
+    }
+}
+
+/// Returns the number of virtual desktops
+///
+/// Reads `_NET_NUMBER_OF_DESKTOPS`.
+///
+/// Returns an error if the property is not set or reading it failed.
+pub fn number_of_desktops(scrn: &Screen) -> Result<u32, Error> {
+    root_cardinal(scrn, "_NET_NUMBER_OF_DESKTOPS")
+}
+
+/// Returns the index of the current virtual desktop
+///
+/// Reads `_NET_CURRENT_DESKTOP`.
+///
+/// Returns an error if the property is not set or reading it failed.
+pub fn current_desktop(scrn: &Screen) -> Result<u32, Error> {
+    root_cardinal(scrn, "_NET_CURRENT_DESKTOP")
+}
+
+/// Returns the names of the virtual desktops
+///
+/// Reads `_NET_DESKTOP_NAMES`. There may be fewer names than desktops.
+///
+/// Returns an error if the property is not set or reading it failed.
+pub fn desktop_names(scrn: &Screen) -> Result<Vec<String>, Error> {
+    root_property(scrn, "_NET_DESKTOP_NAMES", "UTF8_STRING")?.to_utf8_list()
+}
+
+/// Returns the work area of each virtual desktop
+///
+/// Reads `_NET_WORKAREA`, the area of the screen not covered by panels and
+/// docks.
+///
+/// Returns an error if the property is not set or reading it failed.
+pub fn workarea(scrn: &Screen) -> Result<Vec<shapes::PositionedRectangle>, Error> {
+    let values = root_property(scrn, "_NET_WORKAREA", "CARDINAL")?.to_u32s()?;
+    Ok(values.chunks_exact(4)
+        .map(|a| shapes::PositionedRectangle::new(a[0] as i32, a[1] as i32, a[2], a[3]))
+        .collect())
+}
+
+/// Returns the window of the running EWMH compliant window manager
+///
+/// Reads `_NET_SUPPORTING_WM_CHECK` on the root window and checks that the
+/// window it names refers to itself, as stale values may be left behind by
+/// a window manager that exited. Returns `None` if no compliant window
+/// manager is running.
+///
+/// Returns an error if reading the properties failed.
+pub fn supporting_wm<'d>(scrn: &Screen<'d>) -> Result<Option<Window<'d>>, Error> {
+    let d = scrn.display();
+    let check = d.atom("_NET_SUPPORTING_WM_CHECK")?;
+    let ty = d.atom("WINDOW")?;
+    let id = match scrn.root()?.get_property(&check, Some(&ty)) {
+        Ok(p) => match p.to_ids()?.first() {
+            Some(&id) => id,
+            None => return Ok(None)
+        },
+        Err(Error::PropertyMissing(_)) | Err(Error::PropertyType(_)) => return Ok(None),
+        Err(e) => return Err(e)
+    };
+    let w = match d.window(id) {
+        Ok(w) => w,
+        Err(Error::BadWindow(_)) => return Ok(None),
+        Err(e) => return Err(e)
+    };
+    match w.get_property(&check, Some(&ty)) {
+        Ok(p) if p.to_ids()?.first() == Some(&id) => Ok(Some(w)),
+        Ok(_) | Err(Error::PropertyMissing(_)) | Err(Error::PropertyType(_)) => Ok(None),
+        Err(e) => Err(e)
+    }
+}
+
+/// Returns the name of the running EWMH compliant window manager
+///
+/// Reads `_NET_WM_NAME` of the window returned by `supporting_wm()`.
+/// Returns `None` if no compliant window manager is running.
+///
+/// Returns an error if reading the properties failed.
+pub fn wm_name(scrn: &Screen) -> Result<Option<String>, Error> {
+    match supporting_wm(scrn)? {
+        Some(w) => w.title().map(Some),
+        None => Ok(None)
+    }
+}
+
+/// Asks the window manager to activate the window
+///
+/// Sends a `_NET_ACTIVE_WINDOW` request, which usually switches to the
+/// window's desktop, raises and focuses it.
+///
+/// Returns an error if sending the request failed.
+pub fn activate(w: &Window) -> Result<(), Error> {
+    request(w, "_NET_ACTIVE_WINDOW", [SOURCE_PAGER, xlib::CurrentTime as i64, 0, 0, 0])
+}
+
+/// Asks the window manager to close the window
+///
+/// Sends a `_NET_CLOSE_WINDOW` request. The window manager usually closes
+/// the window gracefully, like `Window::close()`.
+///
+/// Returns an error if sending the request failed.
+pub fn close(w: &Window) -> Result<(), Error> {
+    request(w, "_NET_CLOSE_WINDOW", [xlib::CurrentTime as i64, SOURCE_PAGER, 0, 0, 0])
+}
+
+/// Asks the window manager to move and/or resize the window
+///
+/// Sends a `_NET_MOVERESIZE_WINDOW` request. Only the position and size
+/// that are given are changed. The position refers to the reference point
+/// given by `gravity`, or by the window's own gravity if it is `None`.
+///
+/// Returns an error if sending the request failed.
+pub fn move_resize(w: &Window, gravity: Option<window::Gravity>, pos: Option<shapes::Point>, size: Option<shapes::Rectangle>) -> Result<(), Error> {
+    let mut flags = gravity.map(|g| g as i64).unwrap_or(0) | SOURCE_PAGER << 12;
+    let mut data = [0; 5];
+    if let Some(p) = pos {
+        flags |= 1 << 8 | 1 << 9;
+        data[1] = p.x as i64;
+        data[2] = p.y as i64;
+    }
+    if let Some(r) = size {
+        flags |= 1 << 10 | 1 << 11;
+        data[3] = r.w as i64;
+        data[4] = r.h as i64;
+    }
+    data[0] = flags;
+    request(w, "_NET_MOVERESIZE_WINDOW", data)
+}
+
+/// Asks the window manager to move the window to a virtual desktop
+///
+/// Sends a `_NET_WM_DESKTOP` request. Pass `ALL_DESKTOPS` to show the
+/// window on all desktops.
+///
+/// Returns an error if sending the request failed.
+pub fn set_desktop(w: &Window, desktop: u32) -> Result<(), Error> {
+    request(w, "_NET_WM_DESKTOP", [desktop as i64, SOURCE_PAGER, 0, 0, 0])
+}
+
+/// Asks the window manager to switch to a virtual desktop
+///
+/// Sends a `_NET_CURRENT_DESKTOP` request.
+///
+/// Returns an error if sending the request failed.
+pub fn switch_desktop(scrn: &Screen, desktop: u32) -> Result<(), Error> {
+    let root = scrn.root()?;
+    request(&root, "_NET_CURRENT_DESKTOP", [desktop as i64, xlib::CurrentTime as i64, 0, 0, 0])
+}
+
+/// Asks the window manager to change the state of the window
+///
+/// Sends `_NET_WM_STATE` requests that add, remove or toggle `states`, two
+/// at a time. Maximizing a window needs both `State::MaximizedVert` and
+/// `State::MaximizedHorz`.
+///
+/// Returns an error if sending a request failed.
+pub fn set_state(w: &Window, action: StateAction, states: &[State]) -> Result<(), Error> {
+    let d = w.screen().display();
+    for pair in states.chunks(2) {
+        let first = d.atom(pair[0].atom_name())?.id() as i64;
+        let second = match pair.get(1) {
+            Some(s) => d.atom(s.atom_name())?.id() as i64,
+            None => 0
+        };
+        request(w, "_NET_WM_STATE", [action as i64, first, second, SOURCE_PAGER, 0])?;
+    }
+    Ok(())
+}
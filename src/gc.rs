@@ -0,0 +1,293 @@
+use std::mem;
+use std::ptr;
+
+use x11::xlib;
+
+use super::Display;
+use super::Error;
+use super::Window;
+use super::Color;
+use super::shapes;
+
+/// Something that can be drawn on with a `Gc`
+pub trait Drawable {
+    /// Returns the X resource id of the drawable
+    fn drawable(&self) -> u64;
+}
+
+impl<'d> Drawable for Window<'d> {
+    fn drawable(&self) -> u64 {
+        self.id().into()
+    }
+}
+
+/// How lines are drawn
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineStyle {
+    Solid = 0,
+    OnOffDash = 1,
+    DoubleDash = 2
+}
+
+/// Converts a point to the 16-bit coordinates of the protocol
+///
+/// Returns `Error::PointRange` if a coordinate does not fit.
+fn xpoint(p: shapes::Point) -> Result<xlib::XPoint, Error> {
+    match (i16::try_from(p.x), i16::try_from(p.y)) {
+        (Ok(x), Ok(y)) => Ok(xlib::XPoint { x, y }),
+        _ => Err(Error::PointRange(p))
+    }
+}
+
+/// Checks that a rectangle fits into the 16-bit coordinates and sizes of the
+/// protocol
+///
+/// Returns `Error::PointRange` if the position does not fit, or
+/// `Error::SizeRange` if the size does not fit.
+fn check_rectangle(r: shapes::PositionedRectangle) -> Result<(), Error> {
+    xpoint(r.p)?;
+    match (u16::try_from(r.r.w), u16::try_from(r.r.h)) {
+        (Ok(_), Ok(_)) => Ok(()),
+        _ => Err(Error::SizeRange(r.r))
+    }
+}
+
+/// A graphics context for drawing on a drawable
+///
+/// The graphics context is freed when the `Gc` is dropped.
+pub struct Gc<'d> {
+    gc: ptr::NonNull<xlib::_XGC>,
+    target: u64,
+    d: &'d Display
+}
+
+impl<'d> Gc<'d> {
+    /// Creates a graphics context for drawing on `target`
+    ///
+    /// Drawing operations of the returned `Gc` draw on `target`.
+    ///
+    /// Returns an error if the call to `XCreateGC()` failed or the server
+    /// reported an error for it.
+    pub fn new<T: Drawable>(d: &'d Display, target: &T) -> Result<Self, Error> {
+        let start = d.next_request();
+        // SAFETY: display is valid, no values are passed
+        let gc = unsafe {
+            xlib::XCreateGC(d.xlib_display(), target.drawable(), 0, ptr::null_mut())
+        };
+        let gc = ptr::NonNull::new(gc).ok_or(Error::CallFailed("XCreateGC"))?;
+        let gc = Gc { gc, target: target.drawable(), d };
+        d.check(start)?;
+        Ok(gc)
+    }
+
+    fn xlib_gc(&self) -> xlib::GC {
+        self.gc.as_ptr()
+    }
+
+    /// Changes the values of the graphics context selected by `mask`
+    fn change(&self, mask: u64, values: &mut xlib::XGCValues) -> Result<(), Error> {
+        let start = self.d.next_request();
+        // SAFETY: display, gc and values are valid
+        unsafe {
+            // NOTE: XChangeGC() is hardcoded to return 1, so ignore it
+            xlib::XChangeGC(self.d.xlib_display(), self.xlib_gc(), mask, values);
+        }
+        self.d.check(start)
+    }
+
+    /// Sets the color used for drawing
+    ///
+    /// Returns an error if the server reported an error for the change.
+    pub fn set_foreground(&self, color: Color) -> Result<(), Error> {
+        // SAFETY: XGCValues is a C struct, zeroed is valid
+        let mut values: xlib::XGCValues = unsafe { mem::zeroed() };
+        let c: i32 = color.into();
+        values.foreground = c as u64;
+        self.change(xlib::GCForeground as u64, &mut values)
+    }
+
+    /// Sets the color used for the gaps of double-dashed lines
+    ///
+    /// Returns an error if the server reported an error for the change.
+    pub fn set_background(&self, color: Color) -> Result<(), Error> {
+        // SAFETY: XGCValues is a C struct, zeroed is valid
+        let mut values: xlib::XGCValues = unsafe { mem::zeroed() };
+        let c: i32 = color.into();
+        values.background = c as u64;
+        self.change(xlib::GCBackground as u64, &mut values)
+    }
+
+    /// Sets the width of lines in pixels
+    ///
+    /// A width of 0 draws the fastest one pixel wide lines the server
+    /// supports.
+    ///
+    /// Returns an error if the server reported an error for the change.
+    pub fn set_line_width(&self, width: u32) -> Result<(), Error> {
+        // SAFETY: XGCValues is a C struct, zeroed is valid
+        let mut values: xlib::XGCValues = unsafe { mem::zeroed() };
+        values.line_width = width as i32;
+        self.change(xlib::GCLineWidth as u64, &mut values)
+    }
+
+    /// Sets the style of lines
+    ///
+    /// Returns an error if the server reported an error for the change.
+    pub fn set_line_style(&self, style: LineStyle) -> Result<(), Error> {
+        // SAFETY: XGCValues is a C struct, zeroed is valid
+        let mut values: xlib::XGCValues = unsafe { mem::zeroed() };
+        values.line_style = style as i32;
+        self.change(xlib::GCLineStyle as u64, &mut values)
+    }
+
+    /// Fills a rectangle with the foreground color
+    ///
+    /// Returns `Error::PointRange` or `Error::SizeRange` if the rectangle does
+    /// not fit into 16 bits, or an error if the server reported one for the
+    /// request.
+    pub fn fill_rectangle(&self, r: shapes::PositionedRectangle) -> Result<(), Error> {
+        check_rectangle(r)?;
+        let start = self.d.next_request();
+        // SAFETY: display and gc are valid
+        unsafe {
+            // NOTE: XFillRectangle() is hardcoded to return 1, so ignore it
+            xlib::XFillRectangle(self.d.xlib_display(), self.target, self.xlib_gc(), r.p.x, r.p.y, r.r.w, r.r.h);
+        }
+        self.d.check(start)
+    }
+
+    /// Draws the outline of a rectangle
+    ///
+    /// The outline covers `r.r.w + 1` by `r.r.h + 1` pixels.
+    ///
+    /// Returns `Error::PointRange` or `Error::SizeRange` if the rectangle does
+    /// not fit into 16 bits, or an error if the server reported one for the
+    /// request.
+    pub fn draw_rectangle(&self, r: shapes::PositionedRectangle) -> Result<(), Error> {
+        check_rectangle(r)?;
+        let start = self.d.next_request();
+        // SAFETY: display and gc are valid
+        unsafe {
+            // NOTE: XDrawRectangle() is hardcoded to return 1, so ignore it
+            xlib::XDrawRectangle(self.d.xlib_display(), self.target, self.xlib_gc(), r.p.x, r.p.y, r.r.w, r.r.h);
+        }
+        self.d.check(start)
+    }
+
+    /// Draws a line from `from` to `to`
+    ///
+    /// Returns `Error::PointRange` if a coordinate does not fit into 16 bits,
+    /// or an error if the server reported one for the request.
+    pub fn draw_line(&self, from: shapes::Point, to: shapes::Point) -> Result<(), Error> {
+        xpoint(from)?;
+        xpoint(to)?;
+        let start = self.d.next_request();
+        // SAFETY: display and gc are valid
+        unsafe {
+            // NOTE: XDrawLine() is hardcoded to return 1, so ignore it
+            xlib::XDrawLine(self.d.xlib_display(), self.target, self.xlib_gc(), from.x, from.y, to.x, to.y);
+        }
+        self.d.check(start)
+    }
+
+    /// Draws an arc of the ellipse bounded by `r`
+    ///
+    /// The arc starts at `angle` and spans `extent`, both in 64ths of a
+    /// degree counter-clockwise from three o'clock. An extent of `360 * 64`
+    /// draws the whole ellipse.
+    ///
+    /// Returns `Error::PointRange` or `Error::SizeRange` if the rectangle does
+    /// not fit into 16 bits, or an error if the server reported one for the
+    /// request.
+    pub fn draw_arc(&self, r: shapes::PositionedRectangle, angle: i32, extent: i32) -> Result<(), Error> {
+        check_rectangle(r)?;
+        let start = self.d.next_request();
+        // SAFETY: display and gc are valid
+        unsafe {
+            // NOTE: XDrawArc() is hardcoded to return 1, so ignore it
+            xlib::XDrawArc(self.d.xlib_display(), self.target, self.xlib_gc(), r.p.x, r.p.y, r.r.w, r.r.h, angle, extent);
+        }
+        self.d.check(start)
+    }
+
+    /// Fills the polygon with the given corners
+    ///
+    /// The polygon is closed automatically and may intersect itself.
+    ///
+    /// Returns `Error::PointRange` if a coordinate does not fit into 16 bits,
+    /// or an error if the server reported one for the request.
+    pub fn fill_polygon(&self, points: &[shapes::Point]) -> Result<(), Error> {
+        let mut points = points.iter()
+            .map(|&p| xpoint(p))
+            .collect::<Result<Vec<_>, _>>()?;
+        let start = self.d.next_request();
+        // SAFETY: display and gc are valid, points has the passed length
+        unsafe {
+            // NOTE: XFillPolygon() is hardcoded to return 1, so ignore it
+            xlib::XFillPolygon(
+                self.d.xlib_display(), self.target, self.xlib_gc(),
+                points.as_mut_ptr(), points.len() as i32, xlib::Complex, xlib::CoordModeOrigin
+            );
+        }
+        self.d.check(start)
+    }
+
+    /// Copies an area of the drawn-on drawable to `dst`
+    ///
+    /// Copies the area `src` to position `p` in `dst`, e.g. to show an
+    /// off-screen `Pixmap` in a window without flicker. Both drawables must
+    /// have the same depth and root window.
+    ///
+    /// Returns `Error::PointRange` or `Error::SizeRange` if `src` or `p` does
+    /// not fit into 16 bits, or an error if the server reported one for the
+    /// request.
+    pub fn copy_area<T: Drawable>(&self, dst: &T, src: shapes::PositionedRectangle, p: shapes::Point) -> Result<(), Error> {
+        check_rectangle(src)?;
+        xpoint(p)?;
+        let start = self.d.next_request();
+        // SAFETY: display and gc are valid
+        unsafe {
+            // NOTE: XCopyArea() is hardcoded to return 1, so ignore it
+            xlib::XCopyArea(
+                self.d.xlib_display(), self.target, dst.drawable(), self.xlib_gc(),
+                src.p.x, src.p.y, src.r.w, src.r.h, p.x, p.y
+            );
+        }
+        self.d.check(start)
+    }
+}
+
+impl<'d> Drop for Gc<'d> {
+    /// Frees the graphics context when the `Gc` is dropped
+    fn drop(&mut self) {
+        // SAFETY: display and gc are valid, gc is not used afterwards
+        unsafe {
+            // NOTE: XFreeGC() is hardcoded to return 1, so ignore it
+            xlib::XFreeGC(self.d.xlib_display(), self.xlib_gc());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_range() {
+        let p = xpoint(shapes::Point::new(-32768, 32767)).unwrap();
+        assert_eq!((p.x, p.y), (-32768, 32767));
+        let p = shapes::Point::new(32768, 0);
+        assert_eq!(xpoint(p).unwrap_err(), Error::PointRange(p));
+        let p = shapes::Point::new(0, -32769);
+        assert_eq!(xpoint(p).unwrap_err(), Error::PointRange(p));
+    }
+
+    #[test]
+    fn rectangle_range() {
+        assert!(check_rectangle(shapes::PositionedRectangle::new(-10, 20, 65535, 65535)).is_ok());
+        let r = shapes::PositionedRectangle::new(40000, 0, 10, 10);
+        assert_eq!(check_rectangle(r).unwrap_err(), Error::PointRange(r.p));
+        let r = shapes::PositionedRectangle::new(0, 0, 10, 65536);
+        assert_eq!(check_rectangle(r).unwrap_err(), Error::SizeRange(r.r));
+    }
+}
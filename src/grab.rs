@@ -0,0 +1,116 @@
+use std::fmt;
+
+use x11::xlib;
+
+use super::Display;
+use super::keyboard;
+use super::window;
+
+/// Whether event processing continues during a grab
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Events are frozen until `Display::allow_events()` is called
+    Sync = 0,
+    /// Events are processed normally
+    Async = 1
+}
+
+/// Why a grab failed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    AlreadyGrabbed = 1,
+    InvalidTime = 2,
+    NotViewable = 3,
+    Frozen = 4
+}
+
+impl Status {
+    /// Converts the result of a grab request, `None` meaning success
+    pub(super) fn from_result(status: i32) -> Option<Self> {
+        match status {
+            xlib::AlreadyGrabbed => Some(Status::AlreadyGrabbed),
+            xlib::GrabInvalidTime => Some(Status::InvalidTime),
+            xlib::GrabNotViewable => Some(Status::NotViewable),
+            xlib::GrabFrozen => Some(Status::Frozen),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Status::AlreadyGrabbed => write!(f, "already grabbed by another client"),
+            Status::InvalidTime => write!(f, "invalid time"),
+            Status::NotViewable => write!(f, "window not viewable"),
+            Status::Frozen => write!(f, "frozen by another grab")
+        }
+    }
+}
+
+/// How to release events frozen by a synchronous grab
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AllowEvents {
+    AsyncPointer = 0,
+    SyncPointer = 1,
+    /// Releases the grab and sends the frozen button event on as if the grab
+    /// had not happened
+    ReplayPointer = 2,
+    AsyncKeyboard = 3,
+    SyncKeyboard = 4,
+    ReplayKeyboard = 5,
+    AsyncBoth = 6,
+    SyncBoth = 7
+}
+
+/// An active pointer grab
+///
+/// Releases the grab when dropped.
+pub struct PointerGrab<'d> {
+    d: &'d Display
+}
+
+impl<'d> PointerGrab<'d> {
+    pub(super) fn new(d: &'d Display) -> Self {
+        PointerGrab { d }
+    }
+}
+
+impl<'d> Drop for PointerGrab<'d> {
+    /// Releases the grab when the `PointerGrab` is dropped
+    fn drop(&mut self) {
+        self.d.ungrab_pointer();
+        self.d.flush();
+    }
+}
+
+/// An active keyboard grab
+///
+/// Releases the grab when dropped.
+pub struct KeyboardGrab<'d> {
+    d: &'d Display
+}
+
+impl<'d> KeyboardGrab<'d> {
+    pub(super) fn new(d: &'d Display) -> Self {
+        KeyboardGrab { d }
+    }
+}
+
+impl<'d> Drop for KeyboardGrab<'d> {
+    /// Releases the grab when the `KeyboardGrab` is dropped
+    fn drop(&mut self) {
+        self.d.ungrab_keyboard();
+        self.d.flush();
+    }
+}
+
+/// A button grab made through `Window::grab_button()`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) struct ButtonGrab {
+    pub(super) window: window::ID,
+    pub(super) button: u32,
+    pub(super) modifiers: keyboard::Modifiers,
+    /// Lock modifiers that were grabbed in addition to `modifiers`
+    pub(super) ignored: keyboard::Modifiers
+}
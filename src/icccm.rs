@@ -0,0 +1,343 @@
+use super::window;
+use super::window::Gravity;
+use super::shapes;
+
+/// The ICCCM state of a top-level window
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WmState {
+    Withdrawn = 0,
+    Normal = 1,
+    Iconic = 3
+}
+
+impl WmState {
+    pub(super) fn from_item(i: u32) -> Option<Self> {
+        match i {
+            0 => Some(WmState::Withdrawn),
+            1 => Some(WmState::Normal),
+            3 => Some(WmState::Iconic),
+            _ => None
+        }
+    }
+}
+
+/// How a client wants to receive keyboard focus
+///
+/// Determined by the input hint in `WM_HINTS` and whether the client
+/// supports `WM_TAKE_FOCUS`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InputModel {
+    /// The client never expects keyboard input
+    NoInput,
+    /// The client expects to be focused by the window manager
+    Passive,
+    /// The client may also move the focus between its own windows
+    LocallyActive,
+    /// The client decides itself whether to take the focus
+    GloballyActive
+}
+
+/// The window manager hints of a window, as found in `WM_HINTS`
+///
+/// Hints the client did not set are `None`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WmHints {
+    /// Whether the client relies on the window manager to get the focus
+    pub input: Option<bool>,
+    /// The state the window should be in when it is first mapped
+    pub initial_state: Option<WmState>,
+    pub icon_pixmap: Option<u64>,
+    pub icon_window: Option<window::ID>,
+    pub icon_position: Option<shapes::Point>,
+    pub icon_mask: Option<u64>,
+    pub window_group: Option<window::ID>,
+    /// Whether the window needs the user's attention
+    pub urgent: bool
+}
+
+impl WmHints {
+    const INPUT: u32 = 1 << 0;
+    const STATE: u32 = 1 << 1;
+    const ICON_PIXMAP: u32 = 1 << 2;
+    const ICON_WINDOW: u32 = 1 << 3;
+    const ICON_POSITION: u32 = 1 << 4;
+    const ICON_MASK: u32 = 1 << 5;
+    const WINDOW_GROUP: u32 = 1 << 6;
+    const URGENCY: u32 = 1 << 8;
+
+    /// Parses the items of a `WM_HINTS` property
+    ///
+    /// Returns `None` if there are too few items.
+    pub(super) fn from_items(v: &[u32]) -> Option<Self> {
+        if v.len() < 9 {
+            return None;
+        }
+        let flags = v[0];
+        let has = |flag: u32| flags & flag != 0;
+        Some(WmHints {
+            input: Some(v[1] != 0).filter(|_| has(Self::INPUT)),
+            initial_state: WmState::from_item(v[2]).filter(|_| has(Self::STATE)),
+            icon_pixmap: Some(v[3] as u64).filter(|_| has(Self::ICON_PIXMAP)),
+            icon_window: Some(window::ID(v[4] as u64)).filter(|_| has(Self::ICON_WINDOW)),
+            icon_position: Some(shapes::Point::new(v[5] as i32, v[6] as i32)).filter(|_| has(Self::ICON_POSITION)),
+            icon_mask: Some(v[7] as u64).filter(|_| has(Self::ICON_MASK)),
+            window_group: Some(window::ID(v[8] as u64)).filter(|_| has(Self::WINDOW_GROUP)),
+            urgent: has(Self::URGENCY)
+        })
+    }
+
+    /// Returns the items of a `WM_HINTS` property
+    pub(super) fn to_items(&self) -> Vec<u32> {
+        let mut v = vec![0; 9];
+        if let Some(input) = self.input {
+            v[0] |= Self::INPUT;
+            v[1] = input as u32;
+        }
+        if let Some(state) = self.initial_state {
+            v[0] |= Self::STATE;
+            v[2] = state as u32;
+        }
+        if let Some(pixmap) = self.icon_pixmap {
+            v[0] |= Self::ICON_PIXMAP;
+            v[3] = pixmap as u32;
+        }
+        if let Some(w) = self.icon_window {
+            v[0] |= Self::ICON_WINDOW;
+            v[4] = w.0 as u32;
+        }
+        if let Some(p) = self.icon_position {
+            v[0] |= Self::ICON_POSITION;
+            v[5] = p.x as u32;
+            v[6] = p.y as u32;
+        }
+        if let Some(mask) = self.icon_mask {
+            v[0] |= Self::ICON_MASK;
+            v[7] = mask as u32;
+        }
+        if let Some(w) = self.window_group {
+            v[0] |= Self::WINDOW_GROUP;
+            v[8] = w.0 as u32;
+        }
+        if self.urgent {
+            v[0] |= Self::URGENCY;
+        }
+        v
+    }
+}
+
+/// The size hints of a window, as found in `WM_NORMAL_HINTS`
+///
+/// Hints the client did not set are `None`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SizeHints {
+    /// The position was specified by the user
+    pub user_position: bool,
+    /// The size was specified by the user
+    pub user_size: bool,
+    pub min_size: Option<shapes::Rectangle>,
+    pub max_size: Option<shapes::Rectangle>,
+    /// The width and height the window prefers to be resized by
+    pub resize_inc: Option<shapes::Rectangle>,
+    /// The minimum aspect ratio as width and height
+    pub min_aspect: Option<(u32, u32)>,
+    /// The maximum aspect ratio as width and height
+    pub max_aspect: Option<(u32, u32)>,
+    /// The size the resize increments are counted from
+    pub base_size: Option<shapes::Rectangle>,
+    pub win_gravity: Option<Gravity>
+}
+
+impl SizeHints {
+    const US_POSITION: u32 = 1 << 0;
+    const US_SIZE: u32 = 1 << 1;
+    const P_MIN_SIZE: u32 = 1 << 4;
+    const P_MAX_SIZE: u32 = 1 << 5;
+    const P_RESIZE_INC: u32 = 1 << 6;
+    const P_ASPECT: u32 = 1 << 7;
+    const P_BASE_SIZE: u32 = 1 << 8;
+    const P_WIN_GRAVITY: u32 = 1 << 9;
+
+    /// Parses the items of a `WM_SIZE_HINTS` property
+    ///
+    /// Returns `None` if there are too few items. Hints added in ICCCM
+    /// version 1 are optional.
+    pub(super) fn from_items(v: &[u32]) -> Option<Self> {
+        if v.len() < 15 {
+            return None;
+        }
+        let flags = v[0];
+        let has = |flag: u32| flags & flag != 0;
+        let rect = |i: usize| shapes::Rectangle::new(v[i], v[i + 1]);
+        let gravity = match v.get(17) {
+            Some(1) => Some(Gravity::NorthWest),
+            Some(2) => Some(Gravity::North),
+            Some(3) => Some(Gravity::NorthEast),
+            Some(4) => Some(Gravity::West),
+            Some(5) => Some(Gravity::Center),
+            Some(6) => Some(Gravity::East),
+            Some(7) => Some(Gravity::SouthWest),
+            Some(8) => Some(Gravity::South),
+            Some(9) => Some(Gravity::SouthEast),
+            Some(10) => Some(Gravity::Static),
+            _ => None
+        };
+        Some(SizeHints {
+            user_position: has(Self::US_POSITION),
+            user_size: has(Self::US_SIZE),
+            min_size: Some(rect(5)).filter(|_| has(Self::P_MIN_SIZE)),
+            max_size: Some(rect(7)).filter(|_| has(Self::P_MAX_SIZE)),
+            resize_inc: Some(rect(9)).filter(|_| has(Self::P_RESIZE_INC)),
+            min_aspect: Some((v[11], v[12])).filter(|_| has(Self::P_ASPECT)),
+            max_aspect: Some((v[13], v[14])).filter(|_| has(Self::P_ASPECT)),
+            base_size: v.get(16)
+                .map(|_| rect(15))
+                .filter(|_| has(Self::P_BASE_SIZE)),
+            win_gravity: gravity.filter(|_| has(Self::P_WIN_GRAVITY))
+        })
+    }
+
+    /// Returns the size closest to `r` that satisfies the hints
+    ///
+    /// Applies the aspect ratio, resize increments, and minimum and maximum
+    /// size, in that order. As described in the ICCCM, the base size falls
+    /// back to the minimum size and vice versa, and the aspect ratio and
+    /// increments do not apply to the base size.
+    pub fn constrain(&self, r: shapes::Rectangle) -> shapes::Rectangle {
+        let zero = shapes::Rectangle::new(0, 0);
+        let base = self.base_size.or(self.min_size).unwrap_or(zero);
+        let min = self.min_size.or(self.base_size).unwrap_or(zero);
+        let (mut w, mut h) = (r.w as i64, r.h as i64);
+
+        w = (w - base.w as i64).max(0);
+        h = (h - base.h as i64).max(0);
+        if let (Some(min_a), Some(max_a)) = (self.min_aspect, self.max_aspect) {
+            let (min_x, min_y) = (min_a.0 as i64, min_a.1 as i64);
+            let (max_x, max_y) = (max_a.0 as i64, max_a.1 as i64);
+            // NOTE: w / h must lie between min_x / min_y and max_x / max_y
+            if min_x > 0 && min_y > 0 && max_x > 0 && max_y > 0 {
+                if w * max_y > h * max_x {
+                    w = h * max_x / max_y;
+                } else if w * min_y < h * min_x {
+                    h = w * min_y / min_x;
+                }
+            }
+        }
+        if let Some(inc) = self.resize_inc {
+            if inc.w > 0 {
+                w -= w % inc.w as i64;
+            }
+            if inc.h > 0 {
+                h -= h % inc.h as i64;
+            }
+        }
+        w += base.w as i64;
+        h += base.h as i64;
+
+        w = w.max(min.w as i64);
+        h = h.max(min.h as i64);
+        if let Some(max) = self.max_size {
+            if max.w > 0 {
+                w = w.min(max.w as i64);
+            }
+            if max.h > 0 {
+                h = h.min(max.h as i64);
+            }
+        }
+        shapes::Rectangle::new(w.max(1) as u32, h.max(1) as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(w: u32, h: u32) -> shapes::Rectangle {
+        shapes::Rectangle::new(w, h)
+    }
+
+    #[test]
+    fn constrain_without_hints() {
+        let hints = SizeHints::default();
+        assert_eq!(hints.constrain(rect(640, 480)), rect(640, 480));
+        assert_eq!(hints.constrain(rect(0, 0)), rect(1, 1));
+    }
+
+    #[test]
+    fn constrain_min_max() {
+        let hints = SizeHints {
+            min_size: Some(rect(100, 100)),
+            max_size: Some(rect(500, 400)),
+            ..SizeHints::default()
+        };
+        assert_eq!(hints.constrain(rect(100, 100)), rect(100, 100));
+        assert_eq!(hints.constrain(rect(50, 150)), rect(100, 150));
+        assert_eq!(hints.constrain(rect(600, 600)), rect(500, 400));
+    }
+
+    #[test]
+    fn constrain_increments_with_base() {
+        let hints = SizeHints {
+            base_size: Some(rect(10, 20)),
+            resize_inc: Some(rect(7, 13)),
+            ..SizeHints::default()
+        };
+        assert_eq!(hints.constrain(rect(100, 100)), rect(94, 98));
+        assert_eq!(hints.constrain(rect(10, 20)), rect(10, 20));
+        assert_eq!(hints.constrain(rect(5, 5)), rect(10, 20));
+    }
+
+    #[test]
+    fn constrain_increments_from_min() {
+        let hints = SizeHints {
+            min_size: Some(rect(100, 100)),
+            resize_inc: Some(rect(10, 10)),
+            ..SizeHints::default()
+        };
+        assert_eq!(hints.constrain(rect(100, 100)), rect(100, 100));
+        assert_eq!(hints.constrain(rect(155, 109)), rect(150, 100));
+    }
+
+    #[test]
+    fn constrain_aspect() {
+        let hints = SizeHints {
+            min_aspect: Some((1, 1)),
+            max_aspect: Some((2, 1)),
+            ..SizeHints::default()
+        };
+        assert_eq!(hints.constrain(rect(300, 100)), rect(200, 100));
+        assert_eq!(hints.constrain(rect(100, 300)), rect(100, 100));
+        assert_eq!(hints.constrain(rect(150, 100)), rect(150, 100));
+    }
+
+    #[test]
+    fn from_items_too_short() {
+        assert_eq!(SizeHints::from_items(&[0; 14]), None);
+    }
+
+    #[test]
+    fn from_items_pre_icccm() {
+        let flags = SizeHints::P_MIN_SIZE | SizeHints::P_ASPECT | SizeHints::P_BASE_SIZE;
+        let items = [flags, 0, 0, 0, 0, 100, 200, 300, 400, 5, 6, 1, 2, 3, 4];
+        let hints = SizeHints::from_items(&items).unwrap();
+        assert_eq!(hints, SizeHints {
+            min_size: Some(rect(100, 200)),
+            min_aspect: Some((1, 2)),
+            max_aspect: Some((3, 4)),
+            ..SizeHints::default()
+        });
+    }
+
+    #[test]
+    fn from_items_icccm() {
+        let flags = SizeHints::US_SIZE | SizeHints::P_RESIZE_INC | SizeHints::P_BASE_SIZE | SizeHints::P_WIN_GRAVITY;
+        let items = [flags, 0, 0, 0, 0, 0, 0, 0, 0, 5, 6, 0, 0, 0, 0, 7, 8, 10];
+        let hints = SizeHints::from_items(&items).unwrap();
+        assert_eq!(hints, SizeHints {
+            user_size: true,
+            resize_inc: Some(rect(5, 6)),
+            base_size: Some(rect(7, 8)),
+            win_gravity: Some(Gravity::Static),
+            ..SizeHints::default()
+        });
+    }
+}
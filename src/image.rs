@@ -0,0 +1,238 @@
+use std::io;
+use std::ptr;
+
+use x11::xlib;
+
+use super::Display;
+use super::Error;
+use super::Color;
+use super::shapes;
+
+/// Pixels read from the server
+///
+/// Pixels are stored row by row as `0xAARRGGBB`. If the image has no alpha
+/// channel, the alpha byte is always `0xff`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    alpha: bool,
+    pixels: Vec<u32>
+}
+
+/// Extracts the channel selected by `mask` from `pixel` and scales it to
+/// 8 bits
+fn channel(pixel: u64, mask: u64) -> u32 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let bits = (mask >> shift).count_ones();
+    let v = (pixel & mask) >> shift;
+    let v = if bits >= 8 {
+        v >> (bits - 8)
+    } else {
+        v * 255 / ((1 << bits) - 1)
+    };
+    v as u32
+}
+
+impl Image {
+    /// Reads the area `r` of a drawable
+    ///
+    /// Returns an error if the call to `XGetImage()` failed, e.g. if `r` is
+    /// not inside the drawable or a window is not viewable.
+    pub(super) fn capture(d: &Display, drawable: u64, r: shapes::PositionedRectangle) -> Result<Self, Error> {
+        let start = d.next_request();
+        // SAFETY: display is valid
+        let img = unsafe {
+            xlib::XGetImage(d.xlib_display(), drawable, r.p.x, r.p.y, r.r.w, r.r.h, !0, xlib::ZPixmap)
+        };
+        let img = match ptr::NonNull::new(img) {
+            Some(img) => img,
+            None => {
+                // NOTE: report the trapped error instead, if there is one
+                d.sync_since(start)?;
+                return Err(Error::CallFailed("XGetImage"));
+            }
+        };
+        // SAFETY: the image was just returned by XGetImage()
+        let (width, height, depth, red, green, blue) = unsafe {
+            let i = img.as_ref();
+            (i.width as u32, i.height as u32, i.depth as u32, i.red_mask, i.green_mask, i.blue_mask)
+        };
+        // NOTE: only 32-bit visuals have bits left over for alpha
+        let alpha_mask = if depth == 32 {
+            0xffff_ffff & !(red | green | blue)
+        } else {
+            0
+        };
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            for x in 0..width {
+                // SAFETY: the image is valid and (x, y) is inside it
+                let p = unsafe { xlib::XGetPixel(img.as_ptr(), x as i32, y as i32) };
+                let a = if alpha_mask != 0 { channel(p, alpha_mask) } else { 0xff };
+                pixels.push(
+                    (a << 24) | (channel(p, red) << 16) | (channel(p, green) << 8) | channel(p, blue)
+                );
+            }
+        }
+        // SAFETY: the image is not used afterwards
+        unsafe {
+            // NOTE: XDestroyImage() is hardcoded to return 1, so ignore it
+            xlib::XDestroyImage(img.as_ptr());
+        }
+        Ok(Image { width, height, alpha: alpha_mask != 0, pixels })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn size(&self) -> shapes::Rectangle {
+        shapes::Rectangle::new(self.width, self.height)
+    }
+
+    /// Returns whether the pixels have a meaningful alpha channel
+    pub fn has_alpha(&self) -> bool {
+        self.alpha
+    }
+
+    /// Returns all pixels as `0xAARRGGBB`, row by row
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    /// Returns the pixel at `p` as `0xAARRGGBB`
+    ///
+    /// Returns `None` if `p` is outside the image.
+    pub fn argb(&self, p: shapes::Point) -> Option<u32> {
+        if p.x < 0 || p.y < 0 || p.x as u32 >= self.width || p.y as u32 >= self.height {
+            return None;
+        }
+        Some(self.pixels[p.y as usize * self.width as usize + p.x as usize])
+    }
+
+    /// Returns the color of the pixel at `p`, without alpha
+    ///
+    /// Returns `None` if `p` is outside the image.
+    pub fn pixel(&self, p: shapes::Point) -> Option<Color> {
+        self.argb(p).map(|c| Color::unpack((c & 0xff_ffff) as i32))
+    }
+
+    /// Returns the bytes of all pixels as RGB or, with alpha, as RGBA
+    fn bytes(&self, alpha: bool) -> Vec<u8> {
+        let n = if alpha { 4 } else { 3 };
+        let mut bytes = Vec::with_capacity(self.pixels.len() * n);
+        for &c in &self.pixels {
+            bytes.extend_from_slice(&[(c >> 16) as u8, (c >> 8) as u8, c as u8]);
+            if alpha {
+                bytes.push((c >> 24) as u8);
+            }
+        }
+        bytes
+    }
+
+    /// Writes the image as binary PPM (P6)
+    ///
+    /// PPM has no alpha channel, so the alpha channel is dropped.
+    ///
+    /// Returns an error if writing to `w` failed.
+    pub fn write_ppm<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        write!(w, "P6\n{} {}\n255\n", self.width, self.height)?;
+        w.write_all(&self.bytes(false))
+    }
+
+    /// Writes the image as farbfeld
+    ///
+    /// Images without an alpha channel are written as fully opaque.
+    ///
+    /// Returns an error if writing to `w` failed.
+    pub fn write_farbfeld<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        w.write_all(b"farbfeld")?;
+        w.write_all(&self.width.to_be_bytes())?;
+        w.write_all(&self.height.to_be_bytes())?;
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 8);
+        for b in self.bytes(true) {
+            // NOTE: scale 8 to 16 bits so that 0xff becomes 0xffff
+            bytes.extend_from_slice(&(b as u16 * 257).to_be_bytes());
+        }
+        w.write_all(&bytes)
+    }
+
+    /// Writes the image as PNG
+    ///
+    /// Writes RGBA if the image has an alpha channel, and RGB otherwise.
+    ///
+    /// Returns an error if encoding or writing to `w` failed.
+    #[cfg(feature = "png")]
+    pub fn write_png<W: io::Write>(&self, w: W) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(w, self.width, self.height);
+        encoder.set_color(if self.alpha { png::ColorType::Rgba } else { png::ColorType::Rgb });
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.bytes(self.alpha))?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(alpha: bool) -> Image {
+        Image {
+            width: 2,
+            height: 1,
+            alpha,
+            pixels: vec![0x80ff_0000, 0xff01_02ff]
+        }
+    }
+
+    #[test]
+    fn ppm() {
+        let mut out = vec![];
+        image(true).write_ppm(&mut out).unwrap();
+        let mut expected = b"P6\n2 1\n255\n".to_vec();
+        expected.extend_from_slice(&[0xff, 0x00, 0x00, 0x01, 0x02, 0xff]);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn farbfeld() {
+        let mut out = vec![];
+        image(true).write_farbfeld(&mut out).unwrap();
+        assert_eq!(&out[..8], b"farbfeld");
+        assert_eq!(&out[8..12], &[0, 0, 0, 2]);
+        assert_eq!(&out[12..16], &[0, 0, 0, 1]);
+        assert_eq!(&out[16..], &[
+            0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0x80, 0x80,
+            0x01, 0x01, 0x02, 0x02, 0xff, 0xff, 0xff, 0xff
+        ]);
+    }
+
+    #[test]
+    fn farbfeld_opaque() {
+        let mut out = vec![];
+        let mut img = image(false);
+        img.pixels = vec![0xff00_0000, 0xff00_0000];
+        img.write_farbfeld(&mut out).unwrap();
+        assert_eq!(&out[16..24], &[0, 0, 0, 0, 0, 0, 0xff, 0xff]);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png() {
+        let mut out = vec![];
+        image(false).write_png(&mut out).unwrap();
+        assert_eq!(&out[..8], b"\x89PNG\r\n\x1a\n");
+        // NOTE: the IHDR chunk starts with the big-endian width and height
+        assert_eq!(&out[12..24], b"IHDR\0\0\0\x02\0\0\0\x01");
+    }
+}
@@ -0,0 +1,430 @@
+use std::ffi;
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::slice;
+use std::str;
+
+use bitflags::bitflags;
+use x11::keysym;
+use x11::xlib;
+
+use super::Display;
+use super::Error;
+use super::ucs;
+use super::window;
+
+/// A key symbol, e.g. `Return` or `a`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Keysym(pub u64);
+
+impl str::FromStr for Keysym {
+    type Err = Error;
+    /// Looks up a keysym by its name, as used in `keysymdef.h` without the
+    /// `XK_` prefix
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = ffi::CString::new(s)?;
+        // SAFETY: name is a valid C string
+        let sym = unsafe { xlib::XStringToKeysym(name.as_ptr()) };
+        if sym == 0 /* xlib::NoSymbol */ {
+            Err(Error::ParseKey)
+        } else {
+            Ok(Keysym(sym))
+        }
+    }
+}
+
+impl Keysym {
+    /// Returns the name of the keysym, as used in `keysymdef.h` without the
+    /// `XK_` prefix
+    ///
+    /// Returns `None` if the keysym has no name.
+    pub fn name(&self) -> Option<String> {
+        // SAFETY: XKeysymToString() returns a static string or NULL
+        let name = unsafe { xlib::XKeysymToString(self.0) };
+        if name.is_null() {
+            None
+        } else {
+            // SAFETY: name is a valid C string
+            let name = unsafe { ffi::CStr::from_ptr(name) };
+            Some(name.to_string_lossy().into_owned())
+        }
+    }
+
+    /// Returns the printable character the keysym stands for
+    ///
+    /// Handles Latin-1, the legacy keysyms with an exact Unicode equivalent,
+    /// printable keypad keys like `KP_1`, and the `0x1000000` Unicode range.
+    /// Returns `None` for other keysyms and function keys like `Return`.
+    pub fn to_char(&self) -> Option<char> {
+        match self.0 {
+            0x20..=0x7e | 0xa0..=0xff => char::from_u32(self.0 as u32),
+            0x100..=0xfeff => ucs::to_char(self.0),
+            0xff80 /* XK_KP_Space */ => Some(' '),
+            0xffaa..=0xffb9 | 0xffbd /* XK_KP_Multiply to XK_KP_9, XK_KP_Equal */ => char::from_u32((self.0 & 0x7f) as u32),
+            0x100_0100..=0x110_ffff => char::from_u32((self.0 - 0x100_0000) as u32),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Keysym {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "{:#x}", self.0)
+        }
+    }
+}
+
+bitflags! {
+    /// Modifier keys held during a key or button event
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+    pub struct Modifiers: u32 {
+        const SHIFT = xlib::ShiftMask;
+        const LOCK = xlib::LockMask;
+        const CONTROL = xlib::ControlMask;
+        const MOD1 = xlib::Mod1Mask;
+        const MOD2 = xlib::Mod2Mask;
+        const MOD3 = xlib::Mod3Mask;
+        const MOD4 = xlib::Mod4Mask;
+        const MOD5 = xlib::Mod5Mask;
+    }
+}
+
+/// Parses a single modifier name, ignoring case
+///
+/// `Alt` and `Super` are parsed as `alt` and `super_key`.
+fn parse_modifier(s: &str, alt: Modifiers, super_key: Modifiers) -> Result<Modifiers, Error> {
+    match s.to_ascii_lowercase().as_str() {
+        "shift" => Ok(Modifiers::SHIFT),
+        "lock" => Ok(Modifiers::LOCK),
+        "control" | "ctrl" => Ok(Modifiers::CONTROL),
+        "mod1" => Ok(Modifiers::MOD1),
+        "mod2" => Ok(Modifiers::MOD2),
+        "mod3" => Ok(Modifiers::MOD3),
+        "mod4" => Ok(Modifiers::MOD4),
+        "mod5" => Ok(Modifiers::MOD5),
+        "alt" => Ok(alt),
+        "super" => Ok(super_key),
+        _ => Err(Error::ParseKey)
+    }
+}
+
+/// Parses a combination like `Mod4+Shift+Return`
+///
+/// `Alt` and `Super` are parsed as `alt` and `super_key`.
+fn parse_combo(s: &str, alt: Modifiers, super_key: Modifiers) -> Result<KeyCombo, Error> {
+    let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+    let keysym = parts.pop()
+        .filter(|k| !k.is_empty())
+        .ok_or(Error::ParseKey)?
+        .parse()?;
+    let modifiers = parts.into_iter()
+        .map(|m| parse_modifier(m, alt, super_key))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .fold(Modifiers::empty(), |a, m| a | m);
+    Ok(KeyCombo::new(keysym, modifiers))
+}
+
+impl str::FromStr for Modifiers {
+    type Err = Error;
+    /// Parses a single modifier name, ignoring case
+    ///
+    /// Accepts `Shift`, `Lock`, `Control` or `Ctrl`, `Mod1` to `Mod5`, and
+    /// `Alt` and `Super`, which are assumed to be `Mod1` and `Mod4`. Use
+    /// `ModifierMap::parse_combo()` to look them up on the server instead.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_modifier(s, Modifiers::MOD1, Modifiers::MOD4)
+    }
+}
+
+/// A key together with the modifiers that must be held
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyCombo {
+    pub keysym: Keysym,
+    pub modifiers: Modifiers
+}
+
+impl KeyCombo {
+    pub fn new(keysym: Keysym, modifiers: Modifiers) -> KeyCombo {
+        KeyCombo {
+            keysym,
+            modifiers
+        }
+    }
+}
+
+impl str::FromStr for KeyCombo {
+    type Err = Error;
+    /// Parses a combination like `Mod4+Shift+Return`
+    ///
+    /// The last part is the keysym name, all others are modifier names as
+    /// accepted by `Modifiers::from_str()`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_combo(s, Modifiers::MOD1, Modifiers::MOD4)
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const NAMES: [&str; 8] = ["Shift", "Lock", "Control", "Mod1", "Mod2", "Mod3", "Mod4", "Mod5"];
+        for (i, name) in NAMES.iter().enumerate() {
+            if self.modifiers.bits() & (1 << i) != 0 {
+                write!(f, "{}+", name)?;
+            }
+        }
+        write!(f, "{}", self.keysym)
+    }
+}
+
+/// A key grab made through `Window::grab_key()`
+#[derive(Clone, Debug)]
+pub(super) struct Hotkey {
+    pub(super) window: window::ID,
+    pub(super) keycode: u32,
+    /// Lock modifiers that are ignored when matching events
+    pub(super) ignored: Modifiers,
+    pub(super) combo: KeyCombo
+}
+
+impl Hotkey {
+    /// Checks if a key event was caused by this grab
+    pub(super) fn matches(&self, window: window::ID, keycode: u32, state: u32) -> bool {
+        let state = Modifiers::from_bits_truncate(state) - self.ignored;
+        self.window == window && self.keycode == keycode && state == self.combo.modifiers
+    }
+}
+
+/// Which keys set which modifier, as read by `Display::modifier_map()`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModifierMap {
+    /// Keycodes of each of the 8 modifiers
+    keycodes: Vec<Vec<u32>>,
+    /// Keysyms of those keycodes, for each of the 8 modifiers
+    keysyms: Vec<Vec<Keysym>>
+}
+
+impl ModifierMap {
+    /// Reads the modifier map and the keysyms of its keys
+    ///
+    /// Returns an error if the call to `XGetModifierMapping()` or
+    /// `XGetKeyboardMapping()` failed.
+    pub(super) fn read(d: &Display) -> Result<Self, Error> {
+        // SAFETY: display is valid
+        let map = unsafe { xlib::XGetModifierMapping(d.xlib_display()) };
+        if map.is_null() {
+            return Err(Error::CallFailed("XGetModifierMapping"));
+        }
+        // SAFETY: map is valid and has 8 rows of max_keypermod keycodes
+        let keycodes: Vec<Vec<u32>> = unsafe {
+            let n = (*map).max_keypermod as usize;
+            let keycodes = if n == 0 {
+                vec![vec![]; 8]
+            } else {
+                slice::from_raw_parts((*map).modifiermap, 8 * n)
+                    .chunks(n)
+                    .map(|row| row.iter().filter(|&&k| k != 0).map(|&k| k as u32).collect())
+                    .collect()
+            };
+            // NOTE: XFreeModifiermap() is hardcoded to return 1, so ignore it
+            xlib::XFreeModifiermap(map);
+            keycodes
+        };
+        // NOTE: fetch the whole keyboard mapping at once instead of one
+        // round trip per modifier key
+        let (min, max) = d.keycode_range();
+        let mapping = d.keyboard_mapping(min, max - min + 1)?;
+        let keysyms = keycodes.iter()
+            .map(|row| row.iter()
+                .filter_map(|&k| k.checked_sub(min).and_then(|i| mapping.get(i as usize)))
+                .flat_map(|syms| syms.iter().flatten().copied())
+                .collect())
+            .collect();
+        Ok(ModifierMap { keycodes, keysyms })
+    }
+
+    /// Returns the keycodes that set any of the given modifiers
+    pub fn keycodes(&self, modifiers: Modifiers) -> Vec<u32> {
+        self.keycodes.iter()
+            .enumerate()
+            .filter(|(i, _)| modifiers.bits() & (1 << i) != 0)
+            .flat_map(|(_, row)| row.iter().copied())
+            .collect()
+    }
+
+    /// Returns the modifiers set by a key producing any of the keysyms
+    ///
+    /// Returns an empty set if no such key sets a modifier.
+    pub fn modifier_of(&self, keysyms: &[Keysym]) -> Modifiers {
+        self.keysyms.iter()
+            .enumerate()
+            .filter(|(_, row)| row.iter().any(|k| keysyms.contains(k)))
+            .fold(Modifiers::empty(), |a, (i, _)| a | Modifiers::from_bits_truncate(1 << i))
+    }
+
+    /// Returns the modifier set by NumLock, usually `Mod2`
+    pub fn num_lock(&self) -> Modifiers {
+        self.modifier_of(&[Keysym(keysym::XK_Num_Lock as u64)])
+    }
+
+    /// Returns the modifier set by ScrollLock
+    pub fn scroll_lock(&self) -> Modifiers {
+        self.modifier_of(&[Keysym(keysym::XK_Scroll_Lock as u64)])
+    }
+
+    /// Returns the modifier set by the Alt keys, usually `Mod1`
+    ///
+    /// Falls back to the Meta keys if no Alt key sets a modifier.
+    pub fn alt(&self) -> Modifiers {
+        let alt = self.modifier_of(&[Keysym(keysym::XK_Alt_L as u64), Keysym(keysym::XK_Alt_R as u64)]);
+        if alt.is_empty() {
+            self.modifier_of(&[Keysym(keysym::XK_Meta_L as u64), Keysym(keysym::XK_Meta_R as u64)])
+        } else {
+            alt
+        }
+    }
+
+    /// Returns the modifier set by the Super keys, usually `Mod4`
+    pub fn super_key(&self) -> Modifiers {
+        self.modifier_of(&[Keysym(keysym::XK_Super_L as u64), Keysym(keysym::XK_Super_R as u64)])
+    }
+
+    /// Parses a combination like `Super+Shift+Return`
+    ///
+    /// Like `KeyCombo::from_str()`, but `Alt` and `Super` are the modifiers
+    /// their keys actually set. Falls back to `Mod1` and `Mod4` if no such
+    /// key sets a modifier.
+    pub fn parse_combo(&self, s: &str) -> Result<KeyCombo, Error> {
+        let alt = Some(self.alt()).filter(|m| !m.is_empty()).unwrap_or(Modifiers::MOD1);
+        let super_key = Some(self.super_key()).filter(|m| !m.is_empty()).unwrap_or(Modifiers::MOD4);
+        parse_combo(s, alt, super_key)
+    }
+}
+
+/// Returns the modifiers set by CapsLock, NumLock and ScrollLock
+///
+/// Returns an error if reading the modifier map failed.
+pub(super) fn lock_modifiers(d: &Display) -> Result<Modifiers, Error> {
+    let map = d.modifier_map()?;
+    Ok(Modifiers::LOCK | map.num_lock() | map.scroll_lock())
+}
+
+/// Translates a key press like `XLookupString()`
+///
+/// Returns the keysym after applying Shift and Lock in `state`, and the text
+/// it produces. Does not support input methods or compose sequences.
+pub(super) fn lookup(d: &Display, keycode: u32, state: u32) -> (Option<Keysym>, String) {
+    // SAFETY: XKeyEvent is a C struct, zeroed is valid
+    let mut ev: xlib::XKeyEvent = unsafe { mem::zeroed() };
+    ev.type_ = xlib::KeyPress;
+    ev.display = d.xlib_display();
+    ev.keycode = keycode;
+    ev.state = state;
+    let mut buf = [0u8; 32];
+    let mut sym = 0;
+    // SAFETY: event and buffer are valid, no compose status is passed
+    let n = unsafe {
+        xlib::XLookupString(&mut ev, buf.as_mut_ptr() as *mut _, buf.len() as i32, &mut sym, ptr::null_mut())
+    };
+    let keysym = Some(Keysym(sym)).filter(|k| k.0 != 0 /* xlib::NoSymbol */);
+    // NOTE: XLookupString() encodes its text in the locale's charset, so
+    // only trust it for ASCII control characters like Return or Control+C
+    // and take printable text from the keysym
+    let text = match &buf[..n.max(0) as usize] {
+        &[b] if b.is_ascii_control() => char::from(b).to_string(),
+        _ => keysym.and_then(|k| k.to_char()).map(String::from).unwrap_or_default()
+    };
+    (keysym, text)
+}
+
+/// Returns all combinations of the given modifiers
+pub(super) fn combinations(modifiers: Modifiers) -> Vec<Modifiers> {
+    let bits: Vec<Modifiers> = modifiers.iter().collect();
+    (0..1u32 << bits.len())
+        .map(|i| bits.iter()
+            .enumerate()
+            .filter(|(j, _)| i & (1 << j) != 0)
+            .fold(Modifiers::empty(), |a, (_, &m)| a | m))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keysym_to_char() {
+        assert_eq!(Keysym(keysym::XK_a.into()).to_char(), Some('a'));
+        assert_eq!(Keysym(keysym::XK_adiaeresis.into()).to_char(), Some('ä'));
+        assert_eq!(Keysym(keysym::XK_Cyrillic_a.into()).to_char(), Some('а'));
+        assert_eq!(Keysym(keysym::XK_Greek_OMEGA.into()).to_char(), Some('Ω'));
+        assert_eq!(Keysym(0x20ac).to_char(), Some('€'));
+        assert_eq!(Keysym(0x100_20ac).to_char(), Some('€'));
+        assert_eq!(Keysym(keysym::XK_KP_1.into()).to_char(), Some('1'));
+        assert_eq!(Keysym(keysym::XK_KP_Add.into()).to_char(), Some('+'));
+        assert_eq!(Keysym(keysym::XK_Return.into()).to_char(), None);
+        assert_eq!(Keysym(keysym::XK_F1.into()).to_char(), None);
+    }
+
+    #[test]
+    fn combo_from_str() {
+        let combo: KeyCombo = "Mod4+Shift+Return".parse().unwrap();
+        assert_eq!(combo, KeyCombo::new(Keysym(keysym::XK_Return.into()), Modifiers::MOD4 | Modifiers::SHIFT));
+        let combo: KeyCombo = "ctrl + ALT + a".parse().unwrap();
+        assert_eq!(combo, KeyCombo::new(Keysym(keysym::XK_a.into()), Modifiers::CONTROL | Modifiers::MOD1));
+        let combo: KeyCombo = "super+space".parse().unwrap();
+        assert_eq!(combo, KeyCombo::new(Keysym(keysym::XK_space.into()), Modifiers::MOD4));
+    }
+
+    #[test]
+    fn combo_from_str_errors() {
+        assert!(matches!("".parse::<KeyCombo>(), Err(Error::ParseKey)));
+        assert!(matches!("Shift+".parse::<KeyCombo>(), Err(Error::ParseKey)));
+        assert!(matches!("Foo+a".parse::<KeyCombo>(), Err(Error::ParseKey)));
+        assert!(matches!("Shift+NotAKeysym".parse::<KeyCombo>(), Err(Error::ParseKey)));
+    }
+
+    #[test]
+    fn combo_display() {
+        let combo = KeyCombo::new(Keysym(keysym::XK_Return.into()), Modifiers::MOD4 | Modifiers::SHIFT);
+        assert_eq!(combo.to_string(), "Shift+Mod4+Return");
+        assert_eq!(combo.to_string().parse::<KeyCombo>().unwrap(), combo);
+        let combo = KeyCombo::new(Keysym(0x1234_5678), Modifiers::empty());
+        assert_eq!(combo.to_string(), "0x12345678");
+    }
+
+    #[test]
+    fn hotkey_matches() {
+        let hotkey = Hotkey {
+            window: window::ID::from(42),
+            keycode: 36,
+            ignored: Modifiers::LOCK | Modifiers::MOD2,
+            combo: KeyCombo::new(Keysym(keysym::XK_Return.into()), Modifiers::MOD4)
+        };
+        let mod4 = Modifiers::MOD4.bits();
+        assert!(hotkey.matches(window::ID::from(42), 36, mod4));
+        assert!(hotkey.matches(window::ID::from(42), 36, mod4 | xlib::LockMask | xlib::Mod2Mask));
+        // NOTE: pointer button bits in the state are not modifiers
+        assert!(hotkey.matches(window::ID::from(42), 36, mod4 | xlib::Button1Mask));
+        assert!(!hotkey.matches(window::ID::from(42), 36, mod4 | xlib::ShiftMask));
+        assert!(!hotkey.matches(window::ID::from(42), 36, 0));
+        assert!(!hotkey.matches(window::ID::from(42), 37, mod4));
+        assert!(!hotkey.matches(window::ID::from(43), 36, mod4));
+    }
+
+    #[test]
+    fn combinations_of_modifiers() {
+        assert_eq!(combinations(Modifiers::empty()), vec![Modifiers::empty()]);
+        let mods = Modifiers::LOCK | Modifiers::MOD2 | Modifiers::MOD5;
+        let all = combinations(mods);
+        assert_eq!(all.len(), 8);
+        assert!(all.contains(&Modifiers::empty()));
+        assert!(all.contains(&mods));
+        assert!(all.iter().all(|m| mods.contains(*m)));
+        for (i, m) in all.iter().enumerate() {
+            assert!(!all[i + 1..].contains(m));
+        }
+    }
+}
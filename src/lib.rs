@@ -1,12 +1,31 @@
 extern crate x11;
+extern crate bitflags;
+#[cfg(feature = "png")]
+extern crate png;
 
 pub mod display;
 pub mod screen;
 pub mod window;
 pub mod color;
 pub mod shapes;
+pub mod error;
+pub mod event;
+pub mod property;
+pub mod ewmh;
+pub mod icccm;
+pub mod gc;
+pub mod pixmap;
+pub mod image;
+pub mod keyboard;
+mod ucs;
+pub mod cursor;
+pub mod grab;
+#[cfg(feature = "stream")]
+pub mod stream;
 
 pub use display::Display;
 pub use screen::Screen;
 pub use window::Window;
 pub use color::Color;
+pub use error::Error;
+pub use event::Event;
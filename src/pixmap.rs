@@ -0,0 +1,67 @@
+use x11::xlib;
+
+use super::Display;
+use super::Error;
+use super::gc::Drawable;
+use super::shapes;
+
+/// An off-screen drawable
+///
+/// The pixmap is freed when the `Pixmap` is dropped. Draw on it with a
+/// `Gc` and copy the result to a window with `Gc::copy_area()`.
+pub struct Pixmap<'d> {
+    id: u64,
+    size: shapes::Rectangle,
+    depth: u32,
+    d: &'d Display
+}
+
+impl<'d> Pixmap<'d> {
+    /// Creates a pixmap on the screen of `like`
+    ///
+    /// To copy between the pixmap and a window, `depth` must match the
+    /// window's depth, see `Window::depth()`.
+    ///
+    /// Returns an error if the server reported an error for the call to
+    /// `XCreatePixmap()`, e.g. if the size is zero or the depth is not
+    /// supported.
+    pub fn new<T: Drawable>(d: &'d Display, like: &T, size: shapes::Rectangle, depth: u32) -> Result<Self, Error> {
+        let start = d.next_request();
+        // SAFETY: display is valid
+        let id = unsafe {
+            xlib::XCreatePixmap(d.xlib_display(), like.drawable(), size.w, size.h, depth)
+        };
+        // NOTE: only free the pixmap on drop if the server created it
+        d.sync_since(start)?;
+        Ok(Pixmap { id, size, depth, d })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn size(&self) -> shapes::Rectangle {
+        self.size
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+}
+
+impl<'d> Drawable for Pixmap<'d> {
+    fn drawable(&self) -> u64 {
+        self.id
+    }
+}
+
+impl<'d> Drop for Pixmap<'d> {
+    /// Frees the pixmap when the `Pixmap` is dropped
+    fn drop(&mut self) {
+        // SAFETY: display is valid, the pixmap is not used afterwards
+        unsafe {
+            // NOTE: XFreePixmap() is hardcoded to return 1, so ignore it
+            xlib::XFreePixmap(self.d.xlib_display(), self.id);
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::slice;
+
+use super::Display;
+use super::Error;
+use super::display::Atom;
+use super::window;
+
+/// How new data is combined with the existing value of a property
+#[derive(Copy, Clone, Debug)]
+pub enum Mode {
+    Replace = 0,
+    Prepend = 1,
+    Append = 2
+}
+
+/// The items of a property, by format
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Data {
+    Format8(Vec<u8>),
+    Format16(Vec<u16>),
+    Format32(Vec<u32>)
+}
+
+impl Data {
+    /// Copies the items returned by `XGetWindowProperty()`
+    ///
+    /// SAFETY:
+    /// - data must point to n items of the given format, or n must be zero
+    /// - 16-bit items must be stored as shorts and 32-bit items as longs
+    pub(super) unsafe fn from_raw(format: i32, data: *const u8, n: usize) -> Self {
+        if n == 0 || data.is_null() {
+            return match format {
+                16 => Data::Format16(vec![]),
+                32 => Data::Format32(vec![]),
+                _ => Data::Format8(vec![])
+            };
+        }
+        // SAFETY: guaranteed by the caller
+        unsafe {
+            match format {
+                16 => Data::Format16(
+                    slice::from_raw_parts(data as *const i16, n).iter().map(|&i| i as u16).collect()
+                ),
+                32 => Data::Format32(
+                    slice::from_raw_parts(data as *const i64, n).iter().map(|&i| i as u32).collect()
+                ),
+                _ => Data::Format8(slice::from_raw_parts(data, n).to_vec())
+            }
+        }
+    }
+
+    /// Appends the items of `other`
+    ///
+    /// Returns false if `other` has a different format.
+    pub(super) fn append(&mut self, other: Data) -> bool {
+        match (self, other) {
+            (Data::Format8(v), Data::Format8(o)) => v.extend(o),
+            (Data::Format16(v), Data::Format16(o)) => v.extend(o),
+            (Data::Format32(v), Data::Format32(o)) => v.extend(o),
+            _ => return false
+        }
+        true
+    }
+
+    /// Returns the format of the data in bits per item
+    pub fn format(&self) -> i32 {
+        match self {
+            Data::Format8(_) => 8,
+            Data::Format16(_) => 16,
+            Data::Format32(_) => 32
+        }
+    }
+
+    /// Returns the number of items
+    pub fn len(&self) -> usize {
+        match self {
+            Data::Format8(v) => v.len(),
+            Data::Format16(v) => v.len(),
+            Data::Format32(v) => v.len()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A property value read from a window
+#[derive(Clone, Debug)]
+pub struct Property {
+    /// Name of the property
+    pub name: String,
+    /// Id of the atom naming the type of the property
+    pub ty: u64,
+    pub data: Data
+}
+
+impl Property {
+    fn type_error(&self) -> Error {
+        Error::PropertyType(self.name.clone())
+    }
+
+    fn bytes(&self) -> Result<&[u8], Error> {
+        match &self.data {
+            Data::Format8(v) => Ok(v),
+            _ => Err(self.type_error())
+        }
+    }
+
+    /// Returns the items of a 32-bit property, e.g. of type `CARDINAL`
+    ///
+    /// Returns `Error::PropertyType` if the property is not 32-bit.
+    pub fn to_u32s(&self) -> Result<Vec<u32>, Error> {
+        match &self.data {
+            Data::Format32(v) => Ok(v.clone()),
+            _ => Err(self.type_error())
+        }
+    }
+
+    /// Returns the items of a property of type `WINDOW`
+    ///
+    /// Returns `Error::PropertyType` if the property is not 32-bit.
+    pub fn to_ids(&self) -> Result<Vec<window::ID>, Error> {
+        self.to_u32s()
+            .map(|v| v.into_iter().map(|w| window::ID(w as u64)).collect())
+    }
+
+    /// Returns the items of a property of type `ATOM`
+    ///
+    /// Returns `Error::PropertyType` if the property is not 32-bit, or an
+    /// error if an atom name could not be looked up.
+    pub fn to_atoms(&self, d: &Display) -> Result<Vec<Atom>, Error> {
+        self.to_u32s()?
+            .into_iter()
+            .map(|a| d.atom_from_id(a as u64))
+            .collect()
+    }
+
+    /// Returns the value of a property of type `UTF8_STRING`
+    ///
+    /// Returns `Error::PropertyType` if the property is not 8-bit, or
+    /// `Error::PropertyEncoding` if it is not valid UTF-8.
+    pub fn to_utf8(&self) -> Result<String, Error> {
+        let bytes = self.bytes()?;
+        let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| Error::PropertyEncoding(self.name.clone()))
+    }
+
+    /// Returns the value of a property of type `STRING`
+    ///
+    /// Returns `Error::PropertyType` if the property is not 8-bit.
+    pub fn to_latin1(&self) -> Result<String, Error> {
+        let bytes = self.bytes()?;
+        let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+        Ok(bytes.iter().map(|&b| b as char).collect())
+    }
+
+    /// Returns the NUL-separated values of a property of type `UTF8_STRING`
+    ///
+    /// Returns `Error::PropertyType` if the property is not 8-bit, or
+    /// `Error::PropertyEncoding` if it is not valid UTF-8.
+    pub fn to_utf8_list(&self) -> Result<Vec<String>, Error> {
+        let bytes = self.bytes()?;
+        let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+        if bytes.is_empty() {
+            return Ok(vec![]);
+        }
+        bytes.split(|&b| b == 0)
+            .map(|s| String::from_utf8(s.to_vec()).map_err(|_| Error::PropertyEncoding(self.name.clone())))
+            .collect()
+    }
+
+    /// Returns the NUL-separated values of a property of type `STRING`
+    ///
+    /// Returns `Error::PropertyType` if the property is not 8-bit.
+    pub fn to_latin1_list(&self) -> Result<Vec<String>, Error> {
+        let bytes = self.bytes()?;
+        let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+        if bytes.is_empty() {
+            return Ok(vec![]);
+        }
+        Ok(bytes.split(|&b| b == 0)
+            .map(|s| s.iter().map(|&b| b as char).collect())
+            .collect())
+    }
+}
@@ -1,10 +1,14 @@
+use std::marker;
+use std::mem;
 use std::ptr;
 
 use x11::xlib;
 
 use super::Display;
+use super::Error;
 use super::Window;
 use super::shapes;
+use super::image::Image;
 
 #[derive(Clone)]
 pub struct Screen<'d> {
@@ -35,6 +39,10 @@ impl<'d> Screen<'d> {
         self.s.as_ref()
     }
 
+    pub(super) fn display(&self) -> &'d Display {
+        self.d
+    }
+
     pub(super) fn xlib_screen(&self) -> *mut xlib::Screen {
         self.s.as_ptr()
     }
@@ -42,7 +50,7 @@ impl<'d> Screen<'d> {
     /// Returns the pointer coordinates relative to this screen's root window.
     ///
     /// Returns an error if the call to `XQueryPointer()` fails.
-    pub fn pointer(&self) -> Result<shapes::Point, &'static str> {
+    pub fn pointer(&self) -> Result<shapes::Point, Error> {
         let win = self.root()?;
         let ptr = win.pointer_direct()?;
         Ok(ptr.pos)
@@ -51,20 +59,28 @@ impl<'d> Screen<'d> {
     /// Moves the pointer coordinates relative to this screen's root window.
     ///
     /// Returns an error if the call to `XQueryPointer()` fails.
-    pub fn warp_pointer(&self, p: shapes::Point) -> Result<(), &'static str> {
+    pub fn warp_pointer(&self, p: shapes::Point) -> Result<(), Error> {
         let win = self.root()?;
         win.warp_pointer(p)
     }
 
     /// Gets the root window of the screen
     ///
-    /// Returns an error message if the root window does not exist.
+    /// Returns an error if the root window does not exist.
     /// This should never happen.
-    pub fn root(&self) -> Result<Window<'d>, &'static str> {
+    pub fn root(&self) -> Result<Window<'d>, Error> {
         // SAFETY: borrow does not overlap with an X11 call
         Window::new(self.d, unsafe { self.get() }.root.into())
     }
 
+    /// Reads the pixels of the whole screen
+    ///
+    /// Returns an error if the root window does not exist or the call to
+    /// `XGetImage()` failed.
+    pub fn capture(&self) -> Result<Image, Error> {
+        self.root()?.capture(None)
+    }
+
     /// Gets the width of the screen in pixels
     pub fn width(&self) -> u32 {
         // SAFETY: borrow does not overlap with an X11 call
@@ -80,4 +96,80 @@ impl<'d> Screen<'d> {
     pub fn rectangle(&self) -> shapes::PositionedRectangle {
         shapes::PositionedRectangle::new(0, 0, self.width(), self.height())
     }
+
+    /// Gets the number of the screen
+    pub fn number(&self) -> u32 {
+        // SAFETY: screen pointer is valid
+        unsafe { xlib::XScreenNumberOfScreen(self.xlib_screen()) as u32 }
+    }
+
+    /// Gets the default visual of the screen
+    pub fn default_visual(&self) -> Visual<'d> {
+        // SAFETY: screen pointer is valid
+        let (v, depth) = unsafe {
+            (
+                xlib::XDefaultVisualOfScreen(self.xlib_screen()),
+                xlib::XDefaultDepthOfScreen(self.xlib_screen()) as u32
+            )
+        };
+        // SAFETY: the default visual is never null
+        unsafe { Visual::new_unchecked(ptr::NonNull::new_unchecked(v), depth) }
+    }
+
+    /// Finds a TrueColor visual with the given depth
+    ///
+    /// Use a depth of 32 to get a visual with an alpha channel, if the
+    /// server supports it.
+    ///
+    /// Returns an error if the screen has no such visual.
+    pub fn visual(&self, depth: u32) -> Result<Visual<'d>, Error> {
+        // SAFETY: zeroed XVisualInfo is valid
+        let mut info: xlib::XVisualInfo = unsafe { mem::zeroed() };
+        // SAFETY: xlib display is valid
+        let ok = unsafe {
+            xlib::XMatchVisualInfo(
+                self.d.xlib_display(), self.number() as i32,
+                depth as i32, xlib::TrueColor, &mut info
+            ) != 0
+        };
+        match ptr::NonNull::new(info.visual) {
+            // SAFETY: the visual belongs to this screen
+            Some(v) if ok => Ok(unsafe { Visual::new_unchecked(v, depth) }),
+            _ => Err(Error::CallFailed("XMatchVisualInfo"))
+        }
+    }
+}
+
+/// A visual of a screen, describing how pixel values map to colors
+#[derive(Copy, Clone, Debug)]
+pub struct Visual<'d> {
+    v: ptr::NonNull<xlib::Visual>,
+    depth: u32,
+    _d: marker::PhantomData<&'d Display>
+}
+
+impl<'d> Visual<'d> {
+    /// Creates a new Visual object from the given visual pointer
+    ///
+    /// SAFETY:
+    /// - v must come from a display that outlives 'd
+    /// - depth must be a depth supported by the visual
+    unsafe fn new_unchecked(v: ptr::NonNull<xlib::Visual>, depth: u32) -> Self {
+        Visual { v, depth, _d: marker::PhantomData }
+    }
+
+    pub(super) fn xlib_visual(&self) -> *mut xlib::Visual {
+        self.v.as_ptr()
+    }
+
+    /// Gets the id of the visual
+    pub fn id(&self) -> u64 {
+        // SAFETY: visual pointer is valid
+        unsafe { xlib::XVisualIDFromVisual(self.xlib_visual()) }
+    }
+
+    /// Gets the depth used with the visual
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
 }
@@ -1,6 +1,6 @@
 use std::ops;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Point {
     pub x: i32,
     pub y: i32
@@ -9,8 +9,8 @@ pub struct Point {
 impl Point {
     pub fn new(x: i32, y: i32) -> Point {
         Point {
-            x: x,
-            y: y
+            x,
+            y
         }
     }
 }
@@ -29,14 +29,14 @@ impl ops::Sub for Point {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum Pos {
     Left,
     Center,
     Right
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct Corner {
     pub x: Pos,
     pub y: Pos
@@ -54,7 +54,7 @@ impl Corner {
     pub const BOTTOM_RIGHT: Corner = Corner { x: Pos::Right, y: Pos::Right };
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Rectangle {
     pub w: u32,
     pub h: u32
@@ -63,20 +63,20 @@ pub struct Rectangle {
 impl Rectangle {
     pub fn new(w: u32, h: u32) -> Rectangle {
         Rectangle {
-            w: w,
-            h: h
+            w,
+            h
         }
     }
     pub fn pos_x(&self, p: Pos) -> i32 {
         match p {
-            Pos::Left => 0 as i32,
+            Pos::Left => 0,
             Pos::Center => (self.w / 2) as i32,
             Pos::Right => self.w as i32
         }
     }
     pub fn pos_y(&self, p: Pos) -> i32 {
         match p {
-            Pos::Left => 0 as i32,
+            Pos::Left => 0,
             Pos::Center => (self.h / 2) as i32,
             Pos::Right => self.h as i32
         }
@@ -90,7 +90,7 @@ impl Rectangle {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct PositionedRectangle {
     pub p: Point,
     pub r: Rectangle
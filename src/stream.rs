@@ -0,0 +1,59 @@
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use futures_core::Stream;
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+
+use super::Display;
+use super::Event;
+
+/// Asynchronous stream of the events of a display
+///
+/// Waits for the X connection to become readable using the tokio reactor, so
+/// it must be created and polled from within a tokio runtime.
+pub struct EventStream<'d> {
+    d: &'d Display,
+    fd: AsyncFd<RawFd>
+}
+
+impl<'d> EventStream<'d> {
+    /// Creates a new event stream for the display
+    ///
+    /// Returns an error if the connection could not be registered with the
+    /// tokio reactor.
+    pub fn new(d: &'d Display) -> io::Result<Self> {
+        // SAFETY: the connection stays open for the lifetime of the Display,
+        // which outlives the stream
+        let fd = unsafe {
+            AsyncFd::register_with_interest(d.as_raw_fd(), Interest::READABLE)?
+        };
+        Ok(EventStream { d, fd })
+    }
+}
+
+impl Stream for EventStream<'_> {
+    type Item = Event;
+
+    /// Returns the next event, waiting for the connection to become readable
+    ///
+    /// The stream ends if waiting for the connection fails.
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        loop {
+            // NOTE: xlib may have read events into its queue already, so the
+            // queue needs to be checked before waiting for the connection
+            if let Some(e) = self.d.poll_event() {
+                return Poll::Ready(Some(e));
+            }
+            match self.fd.poll_read_ready(cx) {
+                Poll::Ready(Ok(mut guard)) => guard.clear_ready(),
+                Poll::Ready(Err(_)) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+    }
+}
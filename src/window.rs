@@ -1,4 +1,6 @@
+use std::collections;
 use std::convert;
+use std::ffi;
 use std::slice;
 use std::fmt;
 use std::str;
@@ -9,30 +11,55 @@ use x11::xlib;
 
 use super::display;
 use super::Display;
+use super::Error;
 use super::Screen;
+use super::screen::Visual;
 use super::Color;
 use super::shapes;
+use super::event::EventMask;
+use super::display::Atom;
+use super::property;
+use super::icccm;
+use super::pixmap::Pixmap;
+use super::image::Image;
+use super::keyboard;
+use super::grab;
+use super::cursor::Cursor;
+
+/// Checks if an error means that a window does not exist (any more)
+fn is_bad_window(e: &Error) -> bool {
+    match e {
+        Error::BadWindow(_) => true,
+        Error::ProtocolError { code, .. } => *code == xlib::BadWindow,
+        _ => false
+    }
+}
 
 pub struct Window<'d> {
     w: ID,
     d: &'d Display,
-    attrs: xlib::XWindowAttributes
+    attrs: xlib::XWindowAttributes,
+    /// Colormap created for the window by `WindowBuilder::build()`, freed by
+    /// `destroy()`
+    own_colormap: Option<u64>
 }
 
 impl<'d> Window<'d> {
-    pub(super) fn new(d: &'d Display, id: ID) -> Result<Self, &'static str> {
+    pub(super) fn new(d: &'d Display, id: ID) -> Result<Self, Error> {
         let mut w = Window {
             w: id,
-            d: d,
+            d,
             // SAFETY: zeroed XWindowAttributes are valid
             attrs: unsafe {
                 mem::zeroed()
-            }
+            },
+            own_colormap: None
         };
         w.update().map(|_| w)
     }
 
-    fn get_attrs(&self) -> Result<xlib::XWindowAttributes, &'static str> {
+    fn get_attrs(&self) -> Result<xlib::XWindowAttributes, Error> {
+        let start = self.d.next_request();
         // SAFETY: zeroed XWindowAttributes are valid
         let mut attrs = unsafe {
             mem::zeroed()
@@ -46,7 +73,9 @@ impl<'d> Window<'d> {
         if ok {
             Ok(attrs)
         } else {
-            Err("XGetWindowAttributes() failed")
+            // NOTE: the BadWindow error was trapped, but is reported here
+            let _ = self.d.take_error(start);
+            Err(Error::BadWindow(self.w))
         }
     }
 
@@ -55,9 +84,9 @@ impl<'d> Window<'d> {
     /// Gets the window attributes, useful if the window has moved or was
     /// changed otherwise.
     ///
-    /// Returns an error message if the call to `XGetWindowAttributes()`
+    /// Returns an error if the call to `XGetWindowAttributes()`
     /// failed.
-    pub fn update(&mut self) -> Result<(), &'static str> {
+    pub fn update(&mut self) -> Result<(), Error> {
         match self.get_attrs() {
             Ok(a) => {
                 self.attrs = a;
@@ -71,9 +100,9 @@ impl<'d> Window<'d> {
     ///
     /// Moves the window to the coordinates `x` and `y`.
     ///
-    /// Returns an error message if the call to `XConfigureWindow()` or the
+    /// Returns an error if the call to `XConfigureWindow()` or the
     /// call to `XGetWindowAttributes()` after moving failed.
-    pub fn reposition_absolute(&mut self, x: i32, y: i32) -> Result<(), &'static str> {
+    pub fn reposition_absolute(&mut self, x: i32, y: i32) -> Result<(), Error> {
         let mut c = Changes::new();
         c.x(x);
         c.y(y);
@@ -84,9 +113,9 @@ impl<'d> Window<'d> {
     ///
     /// Moves the window by `x` pixels horizontally and `y` pixels vertically.
     ///
-    /// Returns an error message if the call to `XConfigureWindow()` or the
+    /// Returns an error if the call to `XConfigureWindow()` or the
     /// call to `XGetWindowAttributes()` after moving failed.
-    pub fn reposition_relative(&mut self, x: i32, y: i32) -> Result<(), &'static str> {
+    pub fn reposition_relative(&mut self, x: i32, y: i32) -> Result<(), Error> {
         let mut c = Changes::new();
         c.x(self.x() + x);
         c.y(self.y() + y);
@@ -97,9 +126,9 @@ impl<'d> Window<'d> {
     ///
     /// Resizes the window to width `w` and height `h`.
     ///
-    /// Returns an error message if the call to `XConfigureWindow()` or the
+    /// Returns an error if the call to `XConfigureWindow()` or the
     /// call to `XGetWindowAttributes()` after resizing failed.
-    pub fn resize_absolute(&mut self, w: u32, h: u32) -> Result<(), &'static str> {
+    pub fn resize_absolute(&mut self, w: u32, h: u32) -> Result<(), Error> {
         let mut c = Changes::new();
         c.width(w);
         c.height(h);
@@ -111,22 +140,55 @@ impl<'d> Window<'d> {
     /// Resizes the window by `w` pixels horizontally and `h` pixels
     /// vertically.
     ///
-    /// Returns an error message if the call to `XConfigureWindow()` or the
+    /// Returns an error if the call to `XConfigureWindow()` or the
     /// call to `XGetWindowAttributes()` after resizing failed.
-    pub fn resize_relative(&mut self, w: i32, h: i32) -> Result<(), &'static str> {
+    pub fn resize_relative(&mut self, w: i32, h: i32) -> Result<(), Error> {
         let mut c = Changes::new();
         c.width((self.width() as i32 + w) as u32);
         c.height((self.height() as i32 + h) as u32);
         self.change(&c)
     }
 
+    /// Returns the size hints of the window
+    ///
+    /// Reads `WM_NORMAL_HINTS`.
+    ///
+    /// Returns `Error::PropertyMissing` if the property is not set, or an
+    /// error if reading or parsing it failed.
+    pub fn size_hints(&self) -> Result<icccm::SizeHints, Error> {
+        let normal_hints = self.d.atom("WM_NORMAL_HINTS")?;
+        let size_hints = self.d.atom("WM_SIZE_HINTS")?;
+        let items = self.get_property(&normal_hints, Some(&size_hints))?.to_u32s()?;
+        icccm::SizeHints::from_items(&items)
+            .ok_or(Error::PropertyType(normal_hints.name))
+    }
+
+    /// Resizes the window, respecting its size hints
+    ///
+    /// Resizes the window to the size closest to width `w` and height `h`
+    /// that satisfies `WM_NORMAL_HINTS`, see `SizeHints::constrain()`. If
+    /// the window has no size hints, resizes it to exactly that size.
+    ///
+    /// Returns an error if reading the size hints, the call to
+    /// `XConfigureWindow()` or the call to `XGetWindowAttributes()` after
+    /// resizing failed.
+    pub fn resize_hinted(&mut self, w: u32, h: u32) -> Result<(), Error> {
+        let hints = match self.size_hints() {
+            Ok(hints) => hints,
+            Err(Error::PropertyMissing(_)) => icccm::SizeHints::default(),
+            Err(e) => return Err(e)
+        };
+        let r = hints.constrain(shapes::Rectangle::new(w, h));
+        self.resize_absolute(r.w, r.h)
+    }
+
     /// Resizes the window border
     ///
     /// Resizes the window border to `b` pixels
     ///
-    /// Returns an error message if the call to `XConfigureWindow()` or the
+    /// Returns an error if the call to `XConfigureWindow()` or the
     /// call to `XGetWindowAttributes()` after resizing failed.
-    pub fn border_resize(&mut self, b: u32) -> Result<(), &'static str> {
+    pub fn border_resize(&mut self, b: u32) -> Result<(), Error> {
         let mut c = Changes::new();
         c.border_width(b);
         self.change(&c)
@@ -137,9 +199,9 @@ impl<'d> Window<'d> {
     /// Changes window position to the top or bottom of the stack, or inverts
     /// its position depending on `m`.
     ///
-    /// Returns an error message if the call to `XConfigureWindow()` or the
+    /// Returns an error if the call to `XConfigureWindow()` or the
     /// call to `XGetWindowAttributes()` after restacking failed.
-    pub fn restack(&mut self, m: StackMode) -> Result<(), &'static str> {
+    pub fn restack(&mut self, m: StackMode) -> Result<(), Error> {
         let mut c = Changes::new();
         c.stack(m);
         self.change(&c)
@@ -149,9 +211,9 @@ impl<'d> Window<'d> {
     ///
     /// Changes the window border to the color `color`
     ///
-    /// Returns an error message if the call to `XChangeWindowAttributes()` or
+    /// Returns an error if the call to `XChangeWindowAttributes()` or
     /// the call to `XGetWindowAttributes()` after recoloring failed.
-    pub fn border_recolor(&mut self, color: Color) -> Result<(), &'static str> {
+    pub fn border_recolor(&mut self, color: Color) -> Result<(), Error> {
         let mut c = Changes::new();
         c.border_color(color);
         self.change(&c)
@@ -161,41 +223,205 @@ impl<'d> Window<'d> {
     ///
     /// Sets or unsets override_redirect for this window
     ///
-    /// Returns an error message if the call to `XChangeWindowAttributes()` or
+    /// Returns an error if the call to `XChangeWindowAttributes()` or
     /// the call to XGetWindowAttributes()` afterwards failed.
-    pub fn ignore(&mut self, ignore: bool) -> Result<(), &'static str> {
+    pub fn ignore(&mut self, ignore: bool) -> Result<(), Error> {
         let mut c = Changes::new();
         c.ignore(ignore);
         self.change(&c)
     }
 
+    /// Selects the events this client receives for the window
+    ///
+    /// Replaces the event mask previously selected by this client.
+    ///
+    /// Returns an error if the call to `XSelectInput()` or the call to
+    /// `XGetWindowAttributes()` afterwards failed.
+    pub fn select_input(&mut self, mask: EventMask) -> Result<(), Error> {
+        let start = self.d.next_request();
+        // SAFETY: display is valid
+        let ok = unsafe {
+            xlib::XSelectInput(self.d.xlib_display(), self.w.into(), mask.bits()) == 1
+        };
+        if ok {
+            self.d.check(start)?;
+            self.update()
+        } else {
+            Err(Error::CallFailed("XSelectInput"))
+        }
+    }
+
+    /// Grabs a key combination on the window
+    ///
+    /// Key events for the combination are reported to this client while the
+    /// window is viewable, regardless of the focus. Also grabs the
+    /// combination with any of CapsLock, NumLock and ScrollLock active, and
+    /// tags the resulting events with `combo`, see `Display::next_event()`.
+    ///
+    /// Returns `Error::NoKeycode` if no key produces the keysym, or the
+    /// `BadAccess` protocol error if another client already grabbed the
+    /// combination. On error, none of the variants stay grabbed, unless the
+    /// combination was already grabbed on the window before.
+    pub fn grab_key(&self, combo: &keyboard::KeyCombo) -> Result<(), Error> {
+        let start = self.d.next_request();
+        let held = self.d.hotkey(self.id(), *combo).is_some();
+        let keycode = self.d.keycode(combo.keysym)?;
+        let locks = keyboard::lock_modifiers(self.d)? - combo.modifiers;
+        self.grab_variants(start, locks, held, |lock| {
+            // SAFETY: display is valid
+            unsafe {
+                // NOTE: XGrabKey() is hardcoded to return 1, so ignore it
+                xlib::XGrabKey(
+                    self.d.xlib_display(), keycode as i32, (combo.modifiers | lock).bits(), self.id().into(),
+                    true as i32, xlib::GrabModeAsync, xlib::GrabModeAsync
+                );
+            }
+        }, |lock| self.ungrab_key_variant(keycode, combo.modifiers | lock))?;
+        self.d.add_hotkey(keyboard::Hotkey { window: self.id(), keycode, ignored: locks, combo: *combo });
+        Ok(())
+    }
+
+    /// Releases a key combination grabbed with `grab_key()`
+    ///
+    /// Releases the key and lock variants that were grabbed, even if the
+    /// keymap changed since. Looks them up again for combinations not grabbed
+    /// through `grab_key()`.
+    ///
+    /// Returns `Error::NoKeycode` if no key produces the keysym, or an error
+    /// if the server reported one.
+    pub fn ungrab_key(&self, combo: &keyboard::KeyCombo) -> Result<(), Error> {
+        let start = self.d.next_request();
+        let (keycode, locks) = match self.d.hotkey(self.id(), *combo) {
+            Some(h) => (h.keycode, h.ignored),
+            None => (self.d.keycode(combo.keysym)?, keyboard::lock_modifiers(self.d)? - combo.modifiers)
+        };
+        self.d.remove_hotkey(self.id(), *combo);
+        self.ungrab_variants(start, locks, |lock| self.ungrab_key_variant(keycode, combo.modifiers | lock))
+    }
+
+    /// Releases the grab of one key and modifier combination
+    fn ungrab_key_variant(&self, keycode: u32, modifiers: keyboard::Modifiers) {
+        // SAFETY: display is valid
+        unsafe {
+            // NOTE: XUngrabKey() is hardcoded to return 1, so ignore it
+            xlib::XUngrabKey(self.d.xlib_display(), keycode as i32, modifiers.bits(), self.id().into());
+        }
+    }
+
+    /// Grabs a mouse button on the window
+    ///
+    /// When `button` is pressed with `modifiers` held inside the window, the
+    /// pointer is grabbed for this client as if by `Display::grab_pointer()`
+    /// until all buttons are released. Pass 0 as button for any button. Also
+    /// grabs the button with any of CapsLock, NumLock and ScrollLock active.
+    /// With `grab::Mode::Sync`, pointer events are frozen after the press
+    /// until `Display::allow_events()` is called.
+    ///
+    /// Returns the `BadAccess` protocol error if another client already
+    /// grabbed the button, or an error if reading the modifier map failed.
+    /// On error, none of the variants stay grabbed, unless the button was
+    /// already grabbed on the window before.
+    pub fn grab_button(&self, button: u32, modifiers: keyboard::Modifiers, event_mask: EventMask, mode: grab::Mode, cursor: Option<&Cursor>) -> Result<(), Error> {
+        let start = self.d.next_request();
+        let held = self.d.button_grab(self.id(), button, modifiers).is_some();
+        let locks = keyboard::lock_modifiers(self.d)? - modifiers;
+        self.grab_variants(start, locks, held, |lock| {
+            // SAFETY: display is valid
+            unsafe {
+                // NOTE: XGrabButton() is hardcoded to return 1, so ignore it
+                xlib::XGrabButton(
+                    self.d.xlib_display(), button, (modifiers | lock).bits(), self.id().into(),
+                    false as i32, event_mask.bits() as u32, mode as i32, xlib::GrabModeAsync,
+                    0 /* xlib::None */, cursor.map_or(0, Cursor::id)
+                );
+            }
+        }, |lock| self.ungrab_button_variant(button, modifiers | lock))?;
+        self.d.add_button_grab(grab::ButtonGrab { window: self.id(), button, modifiers, ignored: locks });
+        Ok(())
+    }
+
+    /// Releases a mouse button grabbed with `grab_button()`
+    ///
+    /// Releases the lock variants that were grabbed, even if the modifier map
+    /// changed since.
+    ///
+    /// Returns an error if reading the modifier map failed or the server
+    /// reported an error.
+    pub fn ungrab_button(&self, button: u32, modifiers: keyboard::Modifiers) -> Result<(), Error> {
+        let start = self.d.next_request();
+        let locks = match self.d.button_grab(self.id(), button, modifiers) {
+            Some(b) => b.ignored,
+            None => keyboard::lock_modifiers(self.d)? - modifiers
+        };
+        self.d.remove_button_grab(self.id(), button, modifiers);
+        self.ungrab_variants(start, locks, |lock| self.ungrab_button_variant(button, modifiers | lock))
+    }
+
+    /// Releases the grab of one button and modifier combination
+    fn ungrab_button_variant(&self, button: u32, modifiers: keyboard::Modifiers) {
+        // SAFETY: display is valid
+        unsafe {
+            // NOTE: XUngrabButton() is hardcoded to return 1, so ignore it
+            xlib::XUngrabButton(self.d.xlib_display(), button, modifiers.bits(), self.id().into());
+        }
+    }
+
+    /// Makes a passive grab for every combination of the lock modifiers
+    ///
+    /// Calls `grab` for each combination and waits for the server's answer.
+    /// If it refused any of them, calls `ungrab` for each combination unless
+    /// `held` is set, since the granted ones then belong to an earlier grab.
+    fn grab_variants(&self, start: u64, locks: keyboard::Modifiers, held: bool, grab: impl Fn(keyboard::Modifiers), ungrab: impl Fn(keyboard::Modifiers)) -> Result<(), Error> {
+        keyboard::combinations(locks).into_iter().for_each(grab);
+        // NOTE: grabs often conflict with other clients, so always sync
+        let result = self.d.sync_since(start);
+        if result.is_err() && !held {
+            // NOTE: ignore errors of the rollback, the refused grab is the
+            // error to report
+            let start = self.d.next_request();
+            keyboard::combinations(locks).into_iter().for_each(ungrab);
+            let _ = self.d.sync_since(start);
+        }
+        result
+    }
+
+    /// Releases a passive grab for every combination of the lock modifiers
+    fn ungrab_variants(&self, start: u64, locks: keyboard::Modifiers, ungrab: impl Fn(keyboard::Modifiers)) -> Result<(), Error> {
+        keyboard::combinations(locks).into_iter().for_each(ungrab);
+        self.d.check(start)
+    }
+
     /// Maps the window
     ///
-    /// Returns an error message if the call to `XMapWindow()` failed.
-    pub fn map(&mut self) -> Result<(), &'static str> {
+    /// Returns an error if the call to `XMapWindow()` failed.
+    pub fn map(&mut self) -> Result<(), Error> {
+        let start = self.d.next_request();
         // SAFETY: display is valid
         let ok = unsafe {
             xlib::XMapWindow(self.d.xlib_display(), self.w.into()) == 1
         };
         if ok {
+            self.d.check(start)?;
             self.update()
         } else {
-            Err("XMapWindow() failed")
+            Err(Error::CallFailed("XMapWindow"))
         }
     }
 
     /// Unmaps the window
     ///
-    /// Returns an error message if the call to `XUnmapWindow()` failed.
-    pub fn unmap(&mut self) -> Result<(), &'static str> {
+    /// Returns an error if the call to `XUnmapWindow()` failed.
+    pub fn unmap(&mut self) -> Result<(), Error> {
+        let start = self.d.next_request();
         // SAFETY: display is valid
         let ok = unsafe {
             xlib::XUnmapWindow(self.d.xlib_display(), self.w.into()) == 1
         };
         if ok {
+            self.d.check(start)?;
             self.update()
         } else {
-            Err("XUnmapWindow() failed")
+            Err(Error::CallFailed("XUnmapWindow"))
         }
     }
 
@@ -203,135 +429,766 @@ impl<'d> Window<'d> {
     ///
     /// Takes a `Changes` and calls `xlib` functions to apply these changes.
     ///
-    /// Returns an error message if the call to either of these fails:
+    /// Returns an error if the call to either of these fails:
     ///
-    /// - `XChangeWindowAttributes()` if `border_color` or `ignore` state are
-    ///   changed
-    /// - `XConfigureWindow()` if `x`, `y`, `width`, `height` or `border_width`
-    ///   are changed
+    /// - `XChangeWindowAttributes()` if `border_color`, `background_color`,
+    ///   `ignore` state or `event_mask` are changed
+    /// - `XConfigureWindow()` if `x`, `y`, `width`, `height`, `border_width`,
+    ///   `sibling` or `stack` are changed
     /// - `XGetWindowAttributes()`
-    pub fn change(&mut self, c: &Changes) -> Result<(), &'static str> {
-        Ok(()).and_then(|_| {
-            let mut attrs = c.attrs;
-            // SAFETY: display and window attributes are valid
-            let ok = unsafe {
-                xlib::XChangeWindowAttributes(
-                    self.d.xlib_display(), self.id().into(),
-                    c.amask, &mut attrs
-                ) == 1
-            };
-            if ok {
-                Ok(())
-            } else {
-                Err("XChangeWindowAttributes() failed")
-            }
-        }).and_then(|_| {
-            let mut changes = c.changes;
-            // SAFETY: display and changes are valid
-            let ok = unsafe {
-                xlib::XConfigureWindow(
-                    self.d.xlib_display(), self.id().into(),
-                    c.cmask as u32, &mut changes
-                ) == 1
-            };
-            if ok {
-                Ok(())
-            } else {
-                Err("XConfigureWindow() failed")
-            }
-        }).and_then(|_| self.update())
+    ///
+    /// In checked mode, also returns the protocol error caused by the
+    /// changes, if any.
+    pub fn change(&mut self, c: &Changes) -> Result<(), Error> {
+        c.apply(self.d, self.id()).and_then(|_| self.update())
+    }
+
+    /// Reads the pixels of the window
+    ///
+    /// Reads the area `r` relative to the window, or the whole window without
+    /// its border if `r` is `None`. Areas covered by other windows contain
+    /// their contents instead, unless the server keeps a backing store.
+    ///
+    /// Returns an error if the call to `XGetImage()` failed, e.g. if the
+    /// window is not viewable or `r` is not inside it.
+    pub fn capture(&self, r: Option<shapes::PositionedRectangle>) -> Result<Image, Error> {
+        let r = r.unwrap_or_else(|| shapes::PositionedRectangle::new(0, 0, self.width(), self.height()));
+        Image::capture(self.d, self.id().into(), r)
+    }
+
+    /// Clears an area of the window to its background
+    ///
+    /// A width or height of 0 clears to the right or bottom edge of the
+    /// window. If `exposures` is true, generates `Expose` events for the
+    /// cleared area.
+    ///
+    /// Returns an error if the server reported an error for the request.
+    pub fn clear_area(&self, r: shapes::PositionedRectangle, exposures: bool) -> Result<(), Error> {
+        let start = self.d.next_request();
+        // SAFETY: display is valid
+        unsafe {
+            // NOTE: XClearArea() is hardcoded to return 1, so ignore it
+            xlib::XClearArea(self.d.xlib_display(), self.id().into(), r.p.x, r.p.y, r.r.w, r.r.h, exposures as i32);
+        }
+        self.d.check(start)
     }
 
     /// Destroys the window
     ///
-    /// Returns an error message if the call to `XDestroyWindow()` failed. If
-    /// this call succeeds, the window should not exist any more and subsequent
-    /// method calls on the window will return errors.
-    pub fn destroy(&mut self) -> Result<(), &'static str> {
+    /// Also frees the colormap `WindowBuilder::build()` created for the
+    /// window, if any.
+    ///
+    /// Returns an error if the call to `XDestroyWindow()` failed or the
+    /// server reported an error for it. If this call succeeds, the window
+    /// should not exist any more and subsequent method calls on the window
+    /// will return errors.
+    pub fn destroy(&mut self) -> Result<(), Error> {
+        let start = self.d.next_request();
         // SAFETY: display is valid and future calls will error but not be UB
         let ok = unsafe {
             xlib::XDestroyWindow(self.d.xlib_display(), self.id().into()) > 0
         };
-        if ok {
-            Ok(())
-        } else {
-            Err("XDestroyWindow() failed")
+        if !ok {
+            return Err(Error::CallFailed("XDestroyWindow"));
         }
+        if let Some(colormap) = self.own_colormap.take() {
+            // SAFETY: display is valid, the colormap is not used afterwards
+            unsafe {
+                // NOTE: XFreeColormap() is hardcoded to return 1, so ignore it
+                xlib::XFreeColormap(self.d.xlib_display(), colormap);
+            }
+        }
+        self.d.sync_since(start)
     }
 
     /// Destroys the window and kills the controlling client
     ///
-    /// Returns an error message if the call to `XKillClient()` failed. If this
-    /// call succeeds, the window should not exist any more and subsequent
-    /// method calls on the window will return errors.
-    pub fn kill(&mut self) -> Result<(), &'static str> {
+    /// Returns an error if the call to `XKillClient()` failed or the server
+    /// reported an error for it. If this call succeeds, the window should not
+    /// exist any more and subsequent method calls on the window will return
+    /// errors.
+    pub fn kill(&mut self) -> Result<(), Error> {
+        let start = self.d.next_request();
         // SAFETY: display is valid and future calls will error but not be UB
         let ok = unsafe {
             xlib::XKillClient(self.d.xlib_display(), self.id().into()) > 0
         };
         if ok {
-            Ok(())
+            self.d.sync_since(start)
         } else {
-            Err("XDestroyWindow() failed")
+            Err(Error::CallFailed("XKillClient"))
         }
     }
 
-    /// Focuses the window
+    /// Returns the `WM_PROTOCOLS` the window supports
     ///
-    /// Passes `RevertToPointerRoot` and `CurrentTime`.
+    /// Returns an empty list if the property is not set, or an error if
+    /// reading it failed.
+    pub fn protocols(&self) -> Result<Vec<Atom>, Error> {
+        let wm_protocols = self.d.atom("WM_PROTOCOLS")?;
+        let atom = self.d.atom("ATOM")?;
+        match self.get_property(&wm_protocols, Some(&atom)) {
+            Ok(p) => p.to_atoms(self.d),
+            Err(Error::PropertyMissing(_)) => Ok(vec![]),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Checks if the window supports the named `WM_PROTOCOLS` protocol
+    pub(super) fn supports_protocol(&self, name: &str) -> Result<bool, Error> {
+        Ok(self.protocols()?.iter().any(|a| a.name == name))
+    }
+
+    /// Asks the client to close the window
     ///
-    /// Returns an error message if the call to `XSetInputFocus()` failed.
-    pub fn focus(&self) -> Result<(), &'static str> {
-        // SAFETY: display is valid
+    /// Sends a `WM_DELETE_WINDOW` message if the window supports it, which
+    /// lets the client save its data or refuse to close. Otherwise, kills the
+    /// client with `kill()` if `force` is true.
+    ///
+    /// Returns `Error::ProtocolUnsupported` if the window does not support
+    /// `WM_DELETE_WINDOW` and `force` is false, or an error if sending the
+    /// message or killing the client failed.
+    pub fn close(&mut self, force: bool) -> Result<(), Error> {
+        if self.supports_protocol("WM_DELETE_WINDOW")? {
+            let wm_protocols = self.d.atom("WM_PROTOCOLS")?;
+            let delete = self.d.atom("WM_DELETE_WINDOW")?;
+            self.send_client_message(&wm_protocols, [delete.id() as i64, xlib::CurrentTime as i64, 0, 0, 0])
+        } else if force {
+            self.kill()
+        } else {
+            Err(Error::ProtocolUnsupported("WM_DELETE_WINDOW".to_string()))
+        }
+    }
+
+    /// Sends a `ClientMessage` event to this window
+    ///
+    /// Sends an event with format 32 about the window `about` to this
+    /// window, delivered to the clients that selected `mask`. If `mask` is
+    /// empty, the event is delivered to the client that created the window.
+    ///
+    /// Returns an error if the call to `XSendEvent()` failed.
+    pub(super) fn send_client_message_direct(&self, about: ID, mask: EventMask, message_type: &Atom, data: [i64; 5]) -> Result<(), Error> {
+        let start = self.d.next_request();
+        let mut msg = xlib::XClientMessageEvent {
+            type_: xlib::ClientMessage,
+            serial: 0,
+            send_event: true as i32,
+            display: self.d.xlib_display(),
+            window: about.into(),
+            message_type: message_type.id(),
+            format: 32,
+            data: xlib::ClientMessageData::new()
+        };
+        for (i, d) in data.iter().enumerate() {
+            msg.data.set_long(i, *d);
+        }
+        let mut e = xlib::XEvent { client_message: msg };
+        // SAFETY: display and event are valid
         let ok = unsafe {
-            xlib::XSetInputFocus(self.d.xlib_display(), self.id().into(), xlib::RevertToPointerRoot, xlib::CurrentTime) > 0
+            xlib::XSendEvent(self.d.xlib_display(), self.id().into(), false as i32, mask.bits(), &mut e) != 0
         };
         if ok {
-            Ok(())
+            self.d.check(start)
         } else {
-            Err("XSetInputFocus() failed")
+            Err(Error::CallFailed("XSendEvent"))
         }
     }
 
-    /// Returns the children of the window
+    /// Sends a `ClientMessage` event to the client owning this window
     ///
-    /// Returns an error message if the call to `XQueryTree()` failed or if it
-    /// returned NULL with an `n` greater than zero. Also returns errors if any
-    /// of the children give errors at the `XGetWindowAttributes()` call.
-    pub fn children(&self) -> Result<Vec<Window<'d>>, &'static str> {
-        Ok(()).and_then(|_| {
-            let mut _i = (0, 0);
+    /// Sends an event of type `message_type` with format 32 and the given
+    /// data to the window, e.g. for `WM_PROTOCOLS` messages.
+    ///
+    /// Returns an error if the call to `XSendEvent()` failed.
+    pub fn send_client_message(&self, message_type: &Atom, data: [i64; 5]) -> Result<(), Error> {
+        self.send_client_message_direct(self.id(), EventMask::empty(), message_type, data)
+    }
+
+    /// Returns the window manager hints of the window
+    ///
+    /// Reads `WM_HINTS`.
+    ///
+    /// Returns `Error::PropertyMissing` if the property is not set, or an
+    /// error if reading or parsing it failed.
+    pub fn wm_hints(&self) -> Result<icccm::WmHints, Error> {
+        let wm_hints = self.d.atom("WM_HINTS")?;
+        let items = self.get_property(&wm_hints, Some(&wm_hints))?.to_u32s()?;
+        icccm::WmHints::from_items(&items)
+            .ok_or(Error::PropertyType(wm_hints.name))
+    }
+
+    /// Sets the window manager hints of the window
+    ///
+    /// Returns an error if setting `WM_HINTS` failed.
+    pub fn set_wm_hints(&self, hints: &icccm::WmHints) -> Result<(), Error> {
+        let wm_hints = self.d.atom("WM_HINTS")?;
+        let data = property::Data::Format32(hints.to_items());
+        self.set_property(&wm_hints, &wm_hints, &data, property::Mode::Replace)
+    }
+
+    /// Checks if the window has the urgency hint set
+    ///
+    /// Returns false if `WM_HINTS` is not set, or an error if reading it
+    /// failed.
+    pub fn is_urgent(&self) -> Result<bool, Error> {
+        match self.wm_hints() {
+            Ok(hints) => Ok(hints.urgent),
+            Err(Error::PropertyMissing(_)) => Ok(false),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Sets or clears the urgency hint of the window
+    ///
+    /// Keeps the other hints in `WM_HINTS` unchanged.
+    ///
+    /// Returns an error if reading or setting `WM_HINTS` failed.
+    pub fn set_urgent(&self, urgent: bool) -> Result<(), Error> {
+        let mut hints = match self.wm_hints() {
+            Ok(hints) => hints,
+            Err(Error::PropertyMissing(_)) => icccm::WmHints::default(),
+            Err(e) => return Err(e)
+        };
+        hints.urgent = urgent;
+        self.set_wm_hints(&hints)
+    }
+
+    /// Returns the ICCCM state of the window
+    ///
+    /// Reads `WM_STATE`, which the window manager sets on the top-level
+    /// windows of clients it manages. Unlike `mapped()`, this distinguishes
+    /// withdrawn from iconified windows.
+    ///
+    /// Returns `Error::PropertyMissing` if the property is not set, or an
+    /// error if reading or parsing it failed.
+    pub fn wm_state(&self) -> Result<icccm::WmState, Error> {
+        let wm_state = self.d.atom("WM_STATE")?;
+        let items = self.get_property(&wm_state, Some(&wm_state))?.to_u32s()?;
+        items.first()
+            .and_then(|&i| icccm::WmState::from_item(i))
+            .ok_or(Error::PropertyType(wm_state.name))
+    }
+
+    /// Sets the ICCCM state of the window
+    ///
+    /// Writes `WM_STATE` with the given state and icon window. This is meant
+    /// for window managers; clients should use `map()`, `unmap()` and
+    /// `iconify()` instead.
+    ///
+    /// Returns an error if setting `WM_STATE` failed.
+    pub fn set_wm_state(&self, state: icccm::WmState, icon: Option<ID>) -> Result<(), Error> {
+        let wm_state = self.d.atom("WM_STATE")?;
+        let icon = icon.map_or(0, |id| id.0 as u32);
+        let data = property::Data::Format32(vec![state as u32, icon]);
+        self.set_property(&wm_state, &wm_state, &data, property::Mode::Replace)
+    }
+
+    /// Asks the window manager to iconify the window
+    ///
+    /// Sends a `WM_CHANGE_STATE` message to the root window.
+    ///
+    /// Returns an error if sending the message failed.
+    pub fn iconify(&self) -> Result<(), Error> {
+        let root = self.screen().root()?;
+        let change_state = self.d.atom("WM_CHANGE_STATE")?;
+        let mask = EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY;
+        let data = [icccm::WmState::Iconic as i64, 0, 0, 0, 0];
+        root.send_client_message_direct(self.id(), mask, &change_state, data)
+    }
+
+    /// Returns how the client wants to receive keyboard focus
+    ///
+    /// Clients without an input hint are assumed to expect input.
+    ///
+    /// Returns an error if reading `WM_HINTS` or `WM_PROTOCOLS` failed.
+    pub fn input_model(&self) -> Result<icccm::InputModel, Error> {
+        let input = match self.wm_hints() {
+            Ok(hints) => hints.input.unwrap_or(true),
+            Err(Error::PropertyMissing(_)) => true,
+            Err(e) => return Err(e)
+        };
+        let take_focus = self.supports_protocol("WM_TAKE_FOCUS")?;
+        Ok(match (input, take_focus) {
+            (false, false) => icccm::InputModel::NoInput,
+            (true, false) => icccm::InputModel::Passive,
+            (true, true) => icccm::InputModel::LocallyActive,
+            (false, true) => icccm::InputModel::GloballyActive
+        })
+    }
+
+    /// Focuses the window
+    ///
+    /// Follows the ICCCM input model of the window: sets the input focus for
+    /// clients that expect input, sends `WM_TAKE_FOCUS` to clients that
+    /// support it, and does nothing for clients that never expect input.
+    /// Passes `RevertToPointerRoot` and `CurrentTime`.
+    ///
+    /// Returns an error if reading the hints, the call to `XSetInputFocus()`
+    /// or sending `WM_TAKE_FOCUS` failed, or the server reported an error for
+    /// them.
+    pub fn focus(&self) -> Result<(), Error> {
+        let start = self.d.next_request();
+        let model = self.input_model()?;
+        if let icccm::InputModel::Passive | icccm::InputModel::LocallyActive = model {
+            // SAFETY: display is valid
+            let ok = unsafe {
+                xlib::XSetInputFocus(self.d.xlib_display(), self.id().into(), xlib::RevertToPointerRoot, xlib::CurrentTime) > 0
+            };
+            if !ok {
+                return Err(Error::CallFailed("XSetInputFocus"));
+            }
+        }
+        if let icccm::InputModel::LocallyActive | icccm::InputModel::GloballyActive = model {
+            let wm_protocols = self.d.atom("WM_PROTOCOLS")?;
+            let take_focus = self.d.atom("WM_TAKE_FOCUS")?;
+            self.send_client_message(&wm_protocols, [take_focus.id() as i64, xlib::CurrentTime as i64, 0, 0, 0])?;
+        }
+        self.d.sync_since(start)
+    }
+
+    /// Reads a property of the window
+    ///
+    /// Reads the whole value of `property`, even if it spans multiple
+    /// `XGetWindowProperty()` requests. If `ty` is `None`, properties of any
+    /// type are returned.
+    ///
+    /// Returns `Error::PropertyMissing` if the window does not have the
+    /// property, `Error::PropertyType` if it does not have type `ty`, or an
+    /// error if the call to `XGetWindowProperty()` failed.
+    pub fn get_property(&self, property: &Atom, ty: Option<&Atom>) -> Result<property::Property, Error> {
+        let start = self.d.next_request();
+        // NOTE: the length is given in 32-bit units
+        const CHUNK_LENGTH: i64 = 1024;
+        let req_type = ty.map(Atom::id).unwrap_or(0 /* xlib::AnyPropertyType */);
+        let mut offset = 0;
+        let mut result: Option<property::Property> = None;
+        loop {
+            let mut actual_type = 0;
+            let mut format = 0;
             let mut n = 0;
-            let mut ws = ptr::null_mut();
+            let mut bytes_after = 0;
+            let mut data = ptr::null_mut();
             // SAFETY: display is valid
             let ok = unsafe {
-                xlib::XQueryTree(self.d.xlib_display(), self.id().into(), &mut _i.0, &mut _i.1, &mut ws, &mut n) > 0
+                xlib::XGetWindowProperty(
+                    self.d.xlib_display(), self.id().into(), property.id(),
+                    offset, CHUNK_LENGTH, false as i32, req_type,
+                    &mut actual_type, &mut format, &mut n, &mut bytes_after,
+                    &mut data
+                ) == xlib::Success as i32
             };
-            if ok {
-                Ok((ws, n))
-            } else {
-                Err("XQueryTree() failed")
+            if !ok {
+                self.d.take_error(start)?;
+                return Err(Error::CallFailed("XGetWindowProperty"));
             }
-        }).and_then(|res| {
-            let (ws, n) = res;
-            let mut children = vec![];
-            if n == 0 {
-                Ok(children)
-            } else if ws.is_null() {
-                Err("XQueryTree() returned less windows than it promised")
-            } else {
-                let windows = unsafe {
-                    slice::from_raw_parts(ws, n as usize)
+            let n = n as usize;
+            // SAFETY: data holds n items of the returned format, as returned
+            // by xlib, and is freed after copying them
+            let chunk = unsafe {
+                let chunk = property::Data::from_raw(format, data, n);
+                if !data.is_null() {
+                    xlib::XFree(data as *mut _);
+                }
+                chunk
+            };
+            if actual_type == 0 /* xlib::None */ {
+                return Err(Error::PropertyMissing(property.name.clone()));
+            }
+            if req_type != 0 && actual_type != req_type {
+                return Err(Error::PropertyType(property.name.clone()));
+            }
+            offset += (n * format as usize / 32) as i64;
+            match result {
+                Some(ref mut p) => if !p.data.append(chunk) {
+                    // NOTE: the property was replaced while reading it
+                    return Err(Error::PropertyType(property.name.clone()));
+                },
+                None => result = Some(property::Property {
+                    name: property.name.clone(),
+                    ty: actual_type,
+                    data: chunk
+                })
+            }
+            if bytes_after == 0 {
+                break;
+            }
+        }
+        // NOTE: the loop runs at least once and always sets result
+        result.ok_or(Error::CallFailed("XGetWindowProperty"))
+    }
+
+    /// Changes a property of the window
+    ///
+    /// Sets `property` to `data` with type `ty`, or adds `data` to the
+    /// existing value, depending on `mode`. The format is given by the
+    /// variant of `data`.
+    ///
+    /// Returns an error if the call to `XChangeProperty()` failed.
+    pub fn set_property(&self, property: &Atom, ty: &Atom, data: &property::Data, mode: property::Mode) -> Result<(), Error> {
+        let start = self.d.next_request();
+        // NOTE: xlib expects 16-bit items as shorts and 32-bit items as longs
+        let shorts: Vec<i16>;
+        let longs: Vec<i64>;
+        let ptr = match data {
+            property::Data::Format8(v) => v.as_ptr(),
+            property::Data::Format16(v) => {
+                shorts = v.iter().map(|&i| i as i16).collect();
+                shorts.as_ptr() as *const u8
+            },
+            property::Data::Format32(v) => {
+                longs = v.iter().map(|&i| i as i64).collect();
+                longs.as_ptr() as *const u8
+            }
+        };
+        // SAFETY: display is valid, ptr points to data.len() items of the
+        // given format
+        let ok = unsafe {
+            xlib::XChangeProperty(
+                self.d.xlib_display(), self.id().into(), property.id(), ty.id(),
+                data.format(), mode as i32, ptr, data.len() as i32
+            ) == 1
+        };
+        if ok {
+            self.d.check(start)
+        } else {
+            Err(Error::CallFailed("XChangeProperty"))
+        }
+    }
+
+    /// Deletes a property of the window
+    ///
+    /// Returns an error if the call to `XDeleteProperty()` failed.
+    pub fn delete_property(&self, property: &Atom) -> Result<(), Error> {
+        let start = self.d.next_request();
+        // SAFETY: display is valid
+        let ok = unsafe {
+            xlib::XDeleteProperty(self.d.xlib_display(), self.id().into(), property.id()) == 1
+        };
+        if ok {
+            self.d.check(start)
+        } else {
+            Err(Error::CallFailed("XDeleteProperty"))
+        }
+    }
+
+    /// Returns the properties the window has
+    ///
+    /// Returns an error if the call to `XListProperties()` failed or any of
+    /// the property names could not be looked up.
+    pub fn list_properties(&self) -> Result<Vec<Atom>, Error> {
+        let start = self.d.next_request();
+        let mut n = 0;
+        // SAFETY: display is valid
+        let atoms = unsafe {
+            xlib::XListProperties(self.d.xlib_display(), self.id().into(), &mut n)
+        };
+        if atoms.is_null() {
+            // NOTE: a NULL list is also returned if there are no properties
+            return self.d.take_error(start).map(|_| vec![]);
+        }
+        // SAFETY: atoms holds n atoms and is freed after copying them
+        let ids = unsafe {
+            let ids = slice::from_raw_parts(atoms, n as usize).to_vec();
+            xlib::XFree(atoms as *mut _);
+            ids
+        };
+        ids.into_iter()
+            .map(|a| self.d.atom_from_id(a))
+            .collect()
+    }
+
+    /// Decodes a text property of type `STRING`, `UTF8_STRING` or
+    /// `COMPOUND_TEXT`
+    fn decode_text(&self, p: &property::Property) -> Result<String, Error> {
+        let ty = self.d.atom_from_id(p.ty)?;
+        match ty.name.as_str() {
+            "STRING" => p.to_latin1(),
+            "UTF8_STRING" => p.to_utf8(),
+            _ => {
+                let mut bytes = match &p.data {
+                    property::Data::Format8(v) => v.clone(),
+                    _ => return Err(Error::PropertyType(p.name.clone()))
+                };
+                let tp = xlib::XTextProperty {
+                    value: bytes.as_mut_ptr(),
+                    encoding: p.ty,
+                    format: 8,
+                    nitems: bytes.len() as u64
+                };
+                let mut list = ptr::null_mut();
+                let mut n = 0;
+                // SAFETY: display is valid, tp points to bytes which outlives
+                // the call
+                let ok = unsafe {
+                    xlib::Xutf8TextPropertyToTextList(self.d.xlib_display(), &tp, &mut list, &mut n) >= 0
                 };
-                for w in windows {
-                    let w = Window::new(self.d, (*w).into())?;
-                    children.push(w);
+                if !ok || list.is_null() {
+                    return Err(Error::PropertyEncoding(p.name.clone()));
                 }
-                Ok(children)
+                // SAFETY: list holds n C strings and is freed after copying
+                let strings: Vec<String> = unsafe {
+                    let strings = slice::from_raw_parts(list, n as usize)
+                        .iter()
+                        .map(|&s| ffi::CStr::from_ptr(s).to_string_lossy().into_owned())
+                        .collect();
+                    xlib::XFreeStringList(list);
+                    strings
+                };
+                Ok(strings.concat())
             }
+        }
+    }
+
+    /// Returns the title of the window
+    ///
+    /// Prefers `_NET_WM_NAME` and falls back to `WM_NAME`, which may be
+    /// encoded as `STRING`, `UTF8_STRING` or `COMPOUND_TEXT`.
+    ///
+    /// Returns `Error::PropertyMissing` if neither property is set, or an
+    /// error if reading the properties failed.
+    pub fn title(&self) -> Result<String, Error> {
+        let net_wm_name = self.d.atom("_NET_WM_NAME")?;
+        let utf8 = self.d.atom("UTF8_STRING")?;
+        match self.get_property(&net_wm_name, Some(&utf8)) {
+            Ok(p) => return p.to_utf8(),
+            Err(Error::PropertyMissing(_)) | Err(Error::PropertyType(_)) => {},
+            Err(e) => return Err(e)
+        }
+        let wm_name = self.d.atom("WM_NAME")?;
+        let p = self.get_property(&wm_name, None)?;
+        self.decode_text(&p)
+    }
+
+    /// Sets the title of the window
+    ///
+    /// Sets `_NET_WM_NAME` as `UTF8_STRING` and `WM_NAME` as `STRING` or
+    /// `COMPOUND_TEXT`, depending on whether `title` can be represented in
+    /// Latin-1.
+    ///
+    /// Returns an error if `title` contains a NUL byte or setting either
+    /// property failed.
+    pub fn set_title(&self, title: &str) -> Result<(), Error> {
+        let start = self.d.next_request();
+        let cs = ffi::CString::new(title)?;
+        let net_wm_name = self.d.atom("_NET_WM_NAME")?;
+        let utf8 = self.d.atom("UTF8_STRING")?;
+        let data = property::Data::Format8(title.as_bytes().to_vec());
+        self.set_property(&net_wm_name, &utf8, &data, property::Mode::Replace)?;
+
+        // SAFETY: zeroed XTextProperty is valid
+        let mut tp: xlib::XTextProperty = unsafe { mem::zeroed() };
+        let mut list = [cs.as_ptr() as *mut _];
+        // SAFETY: display is valid, list holds one valid C string
+        let ok = unsafe {
+            xlib::Xutf8TextListToTextProperty(
+                self.d.xlib_display(), list.as_mut_ptr(), 1,
+                xlib::XStdICCTextStyle, &mut tp
+            ) >= 0
+        };
+        if !ok {
+            return Err(Error::CallFailed("Xutf8TextListToTextProperty"));
+        }
+        // SAFETY: display is valid, the text property value is freed after
+        // setting it
+        unsafe {
+            xlib::XSetWMName(self.d.xlib_display(), self.id().into(), &mut tp);
+            xlib::XFree(tp.value as *mut _);
+        }
+        self.d.check(start)
+    }
+
+    /// Returns the instance and class name of the window
+    ///
+    /// Reads `WM_CLASS`.
+    ///
+    /// Returns `Error::PropertyMissing` if the property is not set, or an
+    /// error if reading it failed.
+    pub fn class(&self) -> Result<Class, Error> {
+        let wm_class = self.d.atom("WM_CLASS")?;
+        let string = self.d.atom("STRING")?;
+        let mut names = self.get_property(&wm_class, Some(&string))?
+            .to_latin1_list()?
+            .into_iter();
+        Ok(Class {
+            instance: names.next().unwrap_or_default(),
+            class: names.next().unwrap_or_default()
         })
     }
 
+    /// Queries the window tree
+    ///
+    /// Returns the parent of the window, which is `None` for root windows,
+    /// and the ids of its children in stacking order, from bottom to top.
+    fn query_tree(&self) -> Result<(Option<ID>, Vec<ID>), Error> {
+        let start = self.d.next_request();
+        let mut _root = 0;
+        let mut parent = 0;
+        let mut n = 0;
+        let mut ws = ptr::null_mut();
+        // SAFETY: display is valid
+        let ok = unsafe {
+            xlib::XQueryTree(self.d.xlib_display(), self.id().into(), &mut _root, &mut parent, &mut ws, &mut n) > 0
+        };
+        if !ok {
+            self.d.take_error(start)?;
+            return Err(Error::CallFailed("XQueryTree"));
+        }
+        let parent = if parent == 0 /* xlib::None */ {
+            None
+        } else {
+            Some(parent.into())
+        };
+        if n == 0 {
+            Ok((parent, vec![]))
+        } else if ws.is_null() {
+            Err(Error::CallFailed("XQueryTree"))
+        } else {
+            // SAFETY: ws holds n windows and is freed after copying them
+            let children = unsafe {
+                let children = slice::from_raw_parts(ws, n as usize)
+                    .iter()
+                    .map(|&w| w.into())
+                    .collect();
+                xlib::XFree(ws as *mut _);
+                children
+            };
+            Ok((parent, children))
+        }
+    }
+
+    /// Returns the children of the window
+    ///
+    /// Returns an error if the call to `XQueryTree()` failed or if it
+    /// returned NULL with an `n` greater than zero. Also returns errors if any
+    /// of the children give errors at the `XGetWindowAttributes()` call.
+    pub fn children(&self) -> Result<Vec<Window<'d>>, Error> {
+        let (_, ids) = self.query_tree()?;
+        ids.into_iter()
+            .map(|id| Window::new(self.d, id))
+            .collect()
+    }
+
+    /// Returns the parent of the window
+    ///
+    /// Returns `None` if this is a root window.
+    ///
+    /// Returns an error if the call to `XQueryTree()` failed or the parent
+    /// gives an error at the `XGetWindowAttributes()` call.
+    pub fn parent(&self) -> Result<Option<Window<'d>>, Error> {
+        match self.query_tree()? {
+            (Some(id), _) => Window::new(self.d, id).map(Some),
+            (None, _) => Ok(None)
+        }
+    }
+
+    /// Returns the top-level ancestor of the window
+    ///
+    /// This is the ancestor that is a direct child of the root window, which
+    /// is the frame window for clients reparented by a window manager, or the
+    /// window itself if it is top-level. For root windows, returns the
+    /// window itself.
+    ///
+    /// Returns an error if a call to `XQueryTree()` or
+    /// `XGetWindowAttributes()` failed.
+    pub fn toplevel(&self) -> Result<Window<'d>, Error> {
+        let mut w = Window::new(self.d, self.id())?;
+        while let (Some(parent), _) = w.query_tree()? {
+            let p = Window::new(self.d, parent)?;
+            if p.query_tree()?.0.is_none() {
+                break;
+            }
+            w = p;
+        }
+        Ok(w)
+    }
+
+    /// Returns the client window in this window's subtree
+    ///
+    /// Searches the window and its descendants breadth-first for a window
+    /// with `WM_STATE` set, e.g. to find the client reparented into a window
+    /// manager frame. Returns `None` if there is no such window. Descendants
+    /// destroyed during the search are skipped.
+    ///
+    /// Returns an error if reading a property, a call to `XQueryTree()` or a
+    /// call to `XGetWindowAttributes()` failed.
+    pub fn client(&self) -> Result<Option<Window<'d>>, Error> {
+        let wm_state = self.d.atom("WM_STATE")?;
+        let mut queue = collections::VecDeque::from([self.id()]);
+        while let Some(id) = queue.pop_front() {
+            let found = Window::new(self.d, id).and_then(|w| match w.get_property(&wm_state, None) {
+                Ok(_) => Ok(Some(w)),
+                Err(Error::PropertyMissing(_)) => {
+                    queue.extend(w.query_tree()?.1);
+                    Ok(None)
+                },
+                Err(e) => Err(e)
+            });
+            match found {
+                Ok(Some(w)) => return Ok(Some(w)),
+                Ok(None) => {},
+                // NOTE: descendants may be destroyed while searching
+                Err(e) if id != self.id() && is_bad_window(&e) => {},
+                Err(e) => return Err(e)
+            }
+        }
+        Ok(None)
+    }
+
+    /// Moves the window into another parent window
+    ///
+    /// Places the window at position `p` relative to `parent`.
+    ///
+    /// Returns an error if the call to `XReparentWindow()` or the call to
+    /// `XGetWindowAttributes()` after reparenting failed.
+    pub fn reparent(&mut self, parent: &Window, p: shapes::Point) -> Result<(), Error> {
+        let start = self.d.next_request();
+        // SAFETY: display is valid
+        let ok = unsafe {
+            xlib::XReparentWindow(self.d.xlib_display(), self.id().into(), parent.id().into(), p.x, p.y) == 1
+        };
+        if ok {
+            self.d.check(start)?;
+            self.update()
+        } else {
+            Err(Error::CallFailed("XReparentWindow"))
+        }
+    }
+
+    /// Adds the window to this client's save-set
+    ///
+    /// Windows in the save-set are reparented to the closest ancestor that
+    /// is not created by this client and mapped when the connection closes.
+    ///
+    /// Returns an error if the call to `XAddToSaveSet()` failed.
+    pub fn add_to_save_set(&self) -> Result<(), Error> {
+        let start = self.d.next_request();
+        // SAFETY: display is valid
+        let ok = unsafe {
+            xlib::XAddToSaveSet(self.d.xlib_display(), self.id().into()) == 1
+        };
+        if ok {
+            self.d.check(start)
+        } else {
+            Err(Error::CallFailed("XAddToSaveSet"))
+        }
+    }
+
+    /// Removes the window from this client's save-set
+    ///
+    /// Returns an error if the call to `XRemoveFromSaveSet()` failed.
+    pub fn remove_from_save_set(&self) -> Result<(), Error> {
+        let start = self.d.next_request();
+        // SAFETY: display is valid
+        let ok = unsafe {
+            xlib::XRemoveFromSaveSet(self.d.xlib_display(), self.id().into()) == 1
+        };
+        if ok {
+            self.d.check(start)
+        } else {
+            Err(Error::CallFailed("XRemoveFromSaveSet"))
+        }
+    }
+
     /// Gets the screen this window is in
     pub fn screen(&self) -> Screen<'d> {
         // SAFETY: the screen in the window properties is never null
@@ -339,30 +1196,31 @@ impl<'d> Window<'d> {
         unsafe { Screen::new_unchecked(self.d, ptr::NonNull::new_unchecked(self.attrs.screen)) }
     }
 
-    pub(super) fn pointer_direct(&self) -> Result<display::Pointer, &'static str> {
+    pub(super) fn pointer_direct(&self) -> Result<display::Pointer, Error> {
         self.d.pointer_direct(self)
     }
 
     /// Gets the pointer coordinates relative to this window.
     ///
     /// Returns an error if the call to `XQueryPointer()` failed.
-    pub fn pointer(&self) -> Result<shapes::Point, &'static str> {
+    pub fn pointer(&self) -> Result<shapes::Point, Error> {
         let ptr = self.pointer_direct()?;
-        ptr.wpos.ok_or("window not on same screen as pointer")
+        ptr.wpos.ok_or(Error::NotSameScreen)
     }
 
     /// Moves the pointer relative to this window.
     ///
     /// Returns an error if the call to `XWarpPointer()` failed.
-    pub fn warp_pointer(&self, p: shapes::Point) -> Result<(), &'static str> {
+    pub fn warp_pointer(&self, p: shapes::Point) -> Result<(), Error> {
+        let start = self.d.next_request();
         // SAFETY: display is valid
         let ok = unsafe {
             xlib::XWarpPointer(self.d.xlib_display(), 0 /* xlib::None */, self.id().into(), 0, 0, 0, 0, p.x, p.y) > 0
         };
         if ok {
-            Ok(())
+            self.d.check(start)
         } else {
-            Err("XWarpPointer() failed")
+            Err(Error::CallFailed("XWarpPointer"))
         }
     }
 
@@ -373,12 +1231,97 @@ impl<'d> Window<'d> {
     /// Returns true if `XGetWindowAttributes()` didn't return an
     /// error.
     pub fn exists(&self) -> bool {
-        match self.get_attrs() {
-            Ok(_) => true,
-            Err(_) => false
+        self.get_attrs().is_ok()
+    }
+
+    /// Translates coordinates relative to this window to another window
+    ///
+    /// Both coordinates are relative to the inside of the border.
+    ///
+    /// Returns `Error::NotSameScreen` if the windows are on different screens,
+    /// or an error if the call to `XTranslateCoordinates()` failed.
+    pub fn translate_to(&self, other: &Window, p: shapes::Point) -> Result<shapes::Point, Error> {
+        let start = self.d.next_request();
+        let mut dest = shapes::Point::new(0, 0);
+        let mut _child = 0;
+        // SAFETY: display is valid
+        let same_screen = unsafe {
+            xlib::XTranslateCoordinates(
+                self.d.xlib_display(), self.id().into(), other.id().into(),
+                p.x, p.y, &mut dest.x, &mut dest.y, &mut _child
+            ) != 0
+        };
+        self.d.take_error(start)?;
+        if same_screen {
+            Ok(dest)
+        } else {
+            Err(Error::NotSameScreen)
         }
     }
 
+    /// Returns the frame position relative to the root window
+    ///
+    /// Unlike `frame_position()`, this is correct for windows that are not
+    /// direct children of the root window, like clients reparented by a
+    /// window manager.
+    ///
+    /// Returns an error if the call to `XTranslateCoordinates()` failed.
+    pub fn root_position(&self) -> Result<shapes::Point, Error> {
+        let root = self.screen().root()?;
+        let bw = self.border_width() as i32;
+        self.translate_to(&root, shapes::Point::new(-bw, -bw))
+    }
+
+    /// Tells the client the window's geometry with a synthetic
+    /// `ConfigureNotify` event
+    ///
+    /// Window managers send this if they moved or did not change the window
+    /// in response to a `ConfigureRequest`, as required by the ICCCM.
+    ///
+    /// Returns an error if translating the coordinates or the call to
+    /// `XSendEvent()` failed.
+    pub(super) fn send_configure_notify(&self) -> Result<(), Error> {
+        let start = self.d.next_request();
+        let p = self.root_position()?;
+        let ev = xlib::XConfigureEvent {
+            type_: xlib::ConfigureNotify,
+            serial: 0,
+            send_event: true as i32,
+            display: self.d.xlib_display(),
+            event: self.id().into(),
+            window: self.id().into(),
+            x: p.x,
+            y: p.y,
+            width: self.width() as i32,
+            height: self.height() as i32,
+            border_width: self.border_width() as i32,
+            above: 0 /* xlib::None */,
+            override_redirect: self.attrs.override_redirect
+        };
+        let mut e = xlib::XEvent { configure: ev };
+        // SAFETY: display and event are valid
+        let ok = unsafe {
+            xlib::XSendEvent(
+                self.d.xlib_display(), self.id().into(), false as i32,
+                EventMask::STRUCTURE_NOTIFY.bits(), &mut e
+            ) != 0
+        };
+        if ok {
+            self.d.check(start)
+        } else {
+            Err(Error::CallFailed("XSendEvent"))
+        }
+    }
+
+    /// Returns the frame rectangle relative to the root window
+    ///
+    /// Returns an error if the call to `XTranslateCoordinates()` failed.
+    pub fn root_frame(&self) -> Result<shapes::PositionedRectangle, Error> {
+        let p = self.root_position()?;
+        let r = self.frame_size();
+        Ok(shapes::PositionedRectangle::new(p.x, p.y, r.w, r.h))
+    }
+
     pub fn id(&self) -> ID {
         self.w
     }
@@ -399,6 +1342,15 @@ impl<'d> Window<'d> {
         self.attrs.height as u32
     }
 
+    pub fn depth(&self) -> u32 {
+        self.attrs.depth as u32
+    }
+
+    /// Returns the id of the window's colormap
+    pub fn colormap(&self) -> u64 {
+        self.attrs.colormap
+    }
+
     pub fn border_width(&self) -> u32 {
         self.attrs.border_width as u32
     }
@@ -440,18 +1392,56 @@ impl<'d> Window<'d> {
         self.attrs.map_state == xlib::IsViewable
     }
 
+    /// Checks if the window is mapped
+    ///
+    /// Withdrawn and iconified clients are both unmapped; use `wm_state()` to
+    /// tell them apart.
     pub fn mapped(&self) -> bool {
         self.attrs.map_state != xlib::IsUnmapped
     }
+
+    /// Returns the events selected by this client
+    pub fn event_mask(&self) -> EventMask {
+        EventMask::from_bits_retain(self.attrs.your_event_mask)
+    }
+
+    /// Returns the events selected by all clients
+    pub fn all_event_masks(&self) -> EventMask {
+        EventMask::from_bits_retain(self.attrs.all_event_masks)
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
 pub enum StackMode {
     Above = 0,
     Below = 1,
+    TopIf = 2,
+    BottomIf = 3,
     Opposite = 4
 }
 
+/// Reference point of a window used when it is moved or resized
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Gravity {
+    NorthWest = 1,
+    North = 2,
+    NorthEast = 3,
+    West = 4,
+    Center = 5,
+    East = 6,
+    SouthWest = 7,
+    South = 8,
+    SouthEast = 9,
+    Static = 10
+}
+
+/// The instance and class name of a window, as found in `WM_CLASS`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Class {
+    pub instance: String,
+    pub class: String
+}
+
 #[derive(Clone)]
 pub struct Changes {
     changes: xlib::XWindowChanges,
@@ -460,6 +1450,12 @@ pub struct Changes {
     amask: u64
 }
 
+impl Default for Changes {
+    fn default() -> Self {
+        Changes::new()
+    }
+}
+
 impl Changes {
     pub fn new() -> Self {
         Changes {
@@ -469,6 +1465,43 @@ impl Changes {
             amask: 0
         }
     }
+    /// Applies the changes to the window with the given id
+    ///
+    /// Returns an error if the call to `XChangeWindowAttributes()` or
+    /// `XConfigureWindow()` failed, or in checked mode, if the server
+    /// reported an error for them.
+    pub(super) fn apply(&self, d: &Display, id: ID) -> Result<(), Error> {
+        let start = d.next_request();
+        Ok(()).and_then(|_| {
+            let mut attrs = self.attrs;
+            // SAFETY: display and window attributes are valid
+            let ok = unsafe {
+                xlib::XChangeWindowAttributes(
+                    d.xlib_display(), id.into(),
+                    self.amask, &mut attrs
+                ) == 1
+            };
+            if ok {
+                Ok(())
+            } else {
+                Err(Error::CallFailed("XChangeWindowAttributes"))
+            }
+        }).and_then(|_| {
+            let mut changes = self.changes;
+            // SAFETY: display and changes are valid
+            let ok = unsafe {
+                xlib::XConfigureWindow(
+                    d.xlib_display(), id.into(),
+                    self.cmask as u32, &mut changes
+                ) == 1
+            };
+            if ok {
+                Ok(())
+            } else {
+                Err(Error::CallFailed("XConfigureWindow"))
+            }
+        }).and_then(|_| d.check(start))
+    }
     pub fn reset(&mut self) {
         self.changes = unsafe { mem::zeroed() };
         self.attrs = unsafe { mem::zeroed() };
@@ -499,6 +1532,10 @@ impl Changes {
         self.changes.stack_mode = stack as i32;
         self.cmask |= xlib::CWStackMode;
     }
+    pub fn sibling(&mut self, sibling: ID) {
+        self.changes.sibling = sibling.into();
+        self.cmask |= xlib::CWSibling;
+    }
     pub fn border_color(&mut self, border_color: Color) {
         let c: i32 = border_color.into();
         self.attrs.border_pixel = c as u64;
@@ -508,25 +1545,180 @@ impl Changes {
         self.attrs.override_redirect = ignore as i32;
         self.amask |= xlib::CWOverrideRedirect;
     }
+    pub fn event_mask(&mut self, mask: EventMask) {
+        self.attrs.event_mask = mask.bits();
+        self.amask |= xlib::CWEventMask;
+    }
+    pub fn background_color(&mut self, background_color: Color) {
+        let c: i32 = background_color.into();
+        self.attrs.background_pixel = c as u64;
+        self.amask |= xlib::CWBackPixel;
+    }
+    /// Sets a pixmap as background, tiled from the window origin
+    ///
+    /// The server keeps its own reference, so the pixmap may be dropped
+    /// after the changes are applied. It must have the window's depth.
+    pub fn background_pixmap(&mut self, background_pixmap: &Pixmap) {
+        self.attrs.background_pixmap = background_pixmap.id();
+        self.amask |= xlib::CWBackPixmap;
+    }
 }
 
-#[derive(Copy, Clone, Debug)]
+/// Whether a new window can be drawn to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WindowClass {
+    InputOutput = 1,
+    /// The window is invisible and only receives input events
+    InputOnly = 2
+}
+
+/// Creates new windows
+///
+/// Window attributes are set with the same setters as in `Changes`.
+#[derive(Clone)]
+pub struct WindowBuilder<'w, 'd> {
+    parent: &'w Window<'d>,
+    geometry: shapes::PositionedRectangle,
+    border_width: u32,
+    visual: Option<Visual<'d>>,
+    class: WindowClass,
+    changes: Changes
+}
+
+impl<'w, 'd> WindowBuilder<'w, 'd> {
+    /// Creates a builder for a child window of `parent`
+    ///
+    /// By default, the window is a 1x1 InputOutput window at the origin of
+    /// the parent, without border, using the parent's depth and visual.
+    pub fn new(parent: &'w Window<'d>) -> Self {
+        WindowBuilder {
+            parent,
+            geometry: shapes::PositionedRectangle::new(0, 0, 1, 1),
+            border_width: 0,
+            visual: None,
+            class: WindowClass::InputOutput,
+            changes: Changes::new()
+        }
+    }
+    pub fn geometry(&mut self, geometry: shapes::PositionedRectangle) -> &mut Self {
+        self.geometry = geometry;
+        self
+    }
+    pub fn border_width(&mut self, border_width: u32) -> &mut Self {
+        self.border_width = border_width;
+        self
+    }
+    /// Uses the given visual and its depth instead of the parent's
+    pub fn visual(&mut self, visual: Visual<'d>) -> &mut Self {
+        self.visual = Some(visual);
+        self
+    }
+    pub fn class(&mut self, class: WindowClass) -> &mut Self {
+        self.class = class;
+        self
+    }
+    pub fn background_color(&mut self, background_color: Color) -> &mut Self {
+        self.changes.background_color(background_color);
+        self
+    }
+    pub fn border_color(&mut self, border_color: Color) -> &mut Self {
+        self.changes.border_color(border_color);
+        self
+    }
+    pub fn event_mask(&mut self, mask: EventMask) -> &mut Self {
+        self.changes.event_mask(mask);
+        self
+    }
+    pub fn ignore(&mut self, ignore: bool) -> &mut Self {
+        self.changes.ignore(ignore);
+        self
+    }
+
+    /// Creates the window
+    ///
+    /// The window is created unmapped. If a visual other than the parent's
+    /// is used, a matching colormap is created for the window. It is freed
+    /// by `Window::destroy()`, or when the connection is closed if the window
+    /// is never destroyed.
+    ///
+    /// Returns `Error::InputOnlyBorder` if an InputOnly window has a border,
+    /// or an error if the server reported one for the call to
+    /// `XCreateWindow()`, or if the call to `XGetWindowAttributes()`
+    /// afterwards failed. The colormap is freed in that case.
+    pub fn build(&self) -> Result<Window<'d>, Error> {
+        if self.class == WindowClass::InputOnly && self.border_width != 0 {
+            return Err(Error::InputOnlyBorder);
+        }
+        let d = self.parent.d;
+        let start = d.next_request();
+        let mut attrs = self.changes.attrs;
+        let mut amask = self.changes.amask;
+        let mut colormap = None;
+        let (depth, visual) = match (self.class, self.visual) {
+            (WindowClass::InputOnly, _) => {
+                // NOTE: InputOnly windows only support a few attributes
+                amask &= xlib::CWWinGravity | xlib::CWEventMask |
+                    xlib::CWDontPropagate | xlib::CWOverrideRedirect | xlib::CWCursor;
+                (0, ptr::null_mut())
+            },
+            (WindowClass::InputOutput, Some(v)) => {
+                // NOTE: a different visual needs a matching colormap and
+                // border, or the server reports BadMatch
+                // SAFETY: display and visual are valid
+                attrs.colormap = unsafe {
+                    xlib::XCreateColormap(d.xlib_display(), self.parent.id().into(), v.xlib_visual(), xlib::AllocNone)
+                };
+                colormap = Some(attrs.colormap);
+                amask |= xlib::CWColormap | xlib::CWBorderPixel;
+                (v.depth() as i32, v.xlib_visual())
+            },
+            (WindowClass::InputOutput, None) => (0 /* xlib::CopyFromParent */, ptr::null_mut())
+        };
+        let g = self.geometry;
+        // SAFETY: display, visual and attributes are valid
+        let id = unsafe {
+            xlib::XCreateWindow(
+                d.xlib_display(), self.parent.id().into(),
+                g.p.x, g.p.y, g.r.w, g.r.h, self.border_width,
+                depth, self.class as u32, visual, amask, &mut attrs
+            )
+        };
+        match d.sync_since(start).and_then(|_| Window::new(d, id.into())) {
+            Ok(mut w) => {
+                w.own_colormap = colormap;
+                Ok(w)
+            },
+            Err(e) => {
+                if let Some(colormap) = colormap {
+                    // SAFETY: display is valid, the colormap is not used afterwards
+                    unsafe {
+                        // NOTE: XFreeColormap() is hardcoded to return 1, so ignore it
+                        xlib::XFreeColormap(d.xlib_display(), colormap);
+                    }
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ID(pub u64);
 
 impl str::FromStr for ID {
-    type Err = &'static str;
+    type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut hex = s.to_string();
         let is = if hex.len() < 3 {
             false
         } else {
             let pre: String = hex.drain(..2).collect();
-            pre == "0x".to_string()
+            pre == "0x"
         };
         if is {
-            u64::from_str_radix(&hex, 16).map_err(|_| "not a hexadecimal number").map(|u| u.into())
+            u64::from_str_radix(&hex, 16).map_err(|_| Error::ParseID).map(|u| u.into())
         } else {
-            Err("not a hexadecimal number")
+            Err(Error::ParseID)
         }
     }
 }
@@ -537,9 +1729,9 @@ impl convert::From<u64> for ID {
     }
 }
 
-impl convert::Into<u64> for ID {
-    fn into(self) -> u64 {
-        self.0
+impl convert::From<ID> for u64 {
+    fn from(id: ID) -> u64 {
+        id.0
     }
 }
 